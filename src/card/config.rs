@@ -0,0 +1,60 @@
+use std::path::Path;
+use super::face::Face;
+use super::layout::FolderLayout;
+use super::naming::NamingScheme;
+use super::pattern::FilePattern;
+
+/// How the cards of a timeline are named and where their files are in the
+/// card folder. Read from the configuration of the timeline with
+/// carddb::card_config(..).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CardConfig {
+    pub scheme: NamingScheme,
+    pub pattern: FilePattern,
+    pub layout: FolderLayout,
+}
+
+impl CardConfig {
+    /// The card of the name. None if the name is not valid in the scheme.
+    pub fn card(&self, name: &str) -> Option<Face> {
+        return self.scheme.parse(name);
+    }
+
+    /// The card of the file name. None if the file name does not match the
+    /// file pattern.
+    pub fn card_of_file_name(&self, file_name: &str) -> Option<Face> {
+        return self.pattern.parse(&self.scheme, file_name).map(|(card, _)| card);
+    }
+
+    /// The card of the file relative to the card folder. None if the file is
+    /// not a card or is not in the subfolder of the card in the folder layout.
+    pub fn card_of_file(&self, relative: &str) -> Option<Face> {
+        let (subfolder, file_name) = relative.rsplit_once('/').unwrap_or(("", relative));
+        let card = self.card_of_file_name(file_name)?;
+        if self.layout.subfolder(&card) != Path::new(subfolder) {
+            return None;
+        }
+        return Some(card);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_card_of_file() {
+        let config = CardConfig {
+            scheme: NamingScheme::Luhmann,
+            pattern: FilePattern::from_name(Some("{name}-{slug}.md")).unwrap(),
+            layout: FolderLayout::Ranges(100),
+        };
+        assert_eq!(config.card_of_file("0-99/21_3a-notes.md").unwrap().name(), "21/3a");
+        assert_eq!(config.card_of_file("0-99/21_3a.md").unwrap().name(), "21/3a");
+        assert!(config.card_of_file("21_3a.md").is_none());
+        assert!(config.card_of_file("100-199/21_3a.md").is_none());
+        assert!(config.card_of_file("0-99/21a.md").is_none());
+        assert!(CardConfig::default().card_of_file("21a").is_some());
+    }
+}
@@ -1,195 +1,245 @@
 use std::path::{PathBuf, Path};
-use std::cmp::{Eq, Ord, PartialOrd, Ordering};
-
-enum Component {
+use std::cmp::Ordering;
+use std::fs;
+use super::config::CardConfig;
+use super::naming::NamingScheme;
+use super::pattern;
+
+#[derive(Clone, Debug)]
+pub(super) enum Component {
     Number(usize),
     Char(String),
 }
 
+#[derive(Clone, Debug)]
 pub struct Face {
     name_components: Vec<Component>,
+    scheme: NamingScheme,
 }
 
-fn name_components(name: &str) -> Option<Vec<Component>> {
-    if name.len() == 0 {
-        return None;
+/// Letters count like a, b, ..., z, aa, ab, ..., az, ba, ..., zz, aaa.
+fn next_letters(letters: &str) -> String {
+    let mut bytes: Vec<u8> = letters.bytes().collect();
+    for byte in bytes.iter_mut().rev() {
+        if *byte == b'z' {
+            *byte = b'a';
+        } else {
+            *byte += 1;
+            return String::from_utf8(bytes).unwrap();
+        }
     }
+    // Every letter was z.
+    bytes.insert(0, b'a');
+    return String::from_utf8(bytes).unwrap();
+}
 
-    let mut check_first_char = true;
-    for c in name.chars() {
-        let valid = c.is_ascii_digit() || c.is_ascii_lowercase();
-        if !valid {
-            return None;
-        }
+/// Running number of the letters in the order of next_letters(..): a is 1,
+/// z is 26 and aa is 27.
+fn letters_index(letters: &str) -> usize {
+    return letters.bytes().fold(0, |index, byte| index * 26 + (byte - b'a') as usize + 1);
+}
 
-        if check_first_char {
-            check_first_char = false;
-            if !c.is_ascii_digit() {
-                return None;
-            }
-        }
+/// Opposite of letters_index(..): 1 is a, 26 is z and 27 is aa.
+fn index_letters(index: usize) -> String {
+    let mut index = index;
+    let mut bytes: Vec<u8> = Vec::new();
+    while index > 0 {
+        index -= 1;
+        bytes.insert(0, b'a' + (index % 26) as u8);
+        index /= 26;
     }
+    return String::from_utf8(bytes).unwrap();
+}
 
-    let mut chars_iter = name.chars().enumerate().peekable();
-    let mut ranges: Vec<&str> = Vec::new();
-    let mut range_start: usize = 0;
+impl Face {
+    pub(super) fn from_components(name_components: Vec<Component>, scheme: NamingScheme) -> Face {
+        Face { name_components, scheme }
+    }
 
-    loop {
-        let next = chars_iter.next();
-        if next.is_none() {
-            break;
+    pub fn major_number(&self) -> usize {
+        let number = self.name_components.first().unwrap();
+        if let Component::Number(number) = number {
+            return *number;
         }
+        panic!("Not reachable");
+    }
 
-        let (i, c) = next.unwrap();
-        let c_is_digit = c.is_ascii_digit();
-        let c_is_char = c.is_ascii_lowercase();
+    pub fn name(&self) -> String {
+        return self.scheme.format(&self.name_components);
+    }
 
-        let peek = chars_iter.peek();
-        if let Some((_, d)) = peek {
-            let d_is_digit = d.is_ascii_digit();
-            let d_is_char = d.is_ascii_lowercase();
+    /// The card name as it is written in file names. See location_in(..) for
+    /// the name of the card file.
+    pub fn file_name(&self) -> String {
+        return self.scheme.file_name(&self.name());
+    }
 
-            let changed = (c_is_digit && d_is_char) || (c_is_char && d_is_digit);
+    pub fn is_major(&self) -> bool {
+        return self.name_components.len() == 1;
+    }
 
-            if changed {
-                let s: &str = &name[range_start..=i];
-                ranges.push(s);
-                range_start = i + 1;
-            }
-        } else {
-            let s: &str = &name[range_start..=i];
-            ranges.push(s);
-            range_start = i + 1;
+    /// The card file in the file pattern and the folder layout of the
+    /// timeline. The file of the card in the folder if there is one, otherwise
+    /// a file without a slug.
+    pub fn location_in(&self, dir: &Path, config: &CardConfig) -> PathBuf {
+        let pattern = &config.pattern;
+        let dir = config.layout.folder_of(dir, self);
+        let file = dir.join(pattern.file_name(self, ""));
+        if !pattern.has_slug() || file.is_file() {
+            return file;
         }
-    }
 
-    let mut result: Vec<Component> = Vec::new();
-    for range in ranges {
-        let first_char = range.chars().next().unwrap();
-        if first_char.is_ascii_digit() {
-            let number: usize = range.parse().unwrap();
-            result.push(Component::Number(number));
-        } else {
-            let s = String::from(range);
-            result.push(Component::Char(s));
+        // Note(wistrandj): Only the file names with a slug are searched for.
+        let entries = fs::read_dir(&dir).into_iter().flatten().flatten();
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let found = pattern.parse(&self.scheme, &name);
+            if found.is_some_and(|(card, _)| card == *self) && entry.path().is_file() {
+                return entry.path();
+            }
         }
+        return file;
     }
 
-    return Some(result);
-}
+    /// The file for a new card with the slug of the given text.
+    pub fn new_location_in(&self, dir: &Path, config: &CardConfig, text: &str) -> PathBuf {
+        let dir = config.layout.folder_of(dir, self);
+        return dir.join(config.pattern.file_name(self, &pattern::slug_of(text)));
+    }
 
-impl Face {
-    pub fn from_name(name: &str) -> Option<Face> {
-        let comps = name_components(name);
-        if let Some(comps) = comps {
-            return Some(Face {
-                name_components: comps
-            });
-        } else {
+    /// The card this one branches off: 123a1 -> 123a, 123a -> 123. None for a
+    /// major card.
+    pub fn parent(&self) -> Option<Face> {
+        if self.is_major() {
             return None;
         }
+        let mut name_components = self.name_components.clone();
+        name_components.pop();
+        return Some(Face { name_components, scheme: self.scheme });
     }
 
-    pub fn from_number(major_number: usize) -> Face {
-        Face {
-            name_components: vec![Component::Number(major_number)],
+    /// Position of the card among its siblings: 1 for 123a and 123a1, 2 for
+    /// 123b and 123a2, 27 for 123aa.
+    pub fn sibling_index(&self) -> usize {
+        match self.name_components.last().unwrap() {
+            Component::Number(number) => *number,
+            Component::Char(chars) => letters_index(chars),
         }
     }
 
-    pub fn major_number(&self) -> usize {
-        let number = self.name_components.get(0).unwrap();
-        if let Component::Number(number) = number {
-            return number.clone();
-        }
-        panic!("Not reachable");
+    /// Whether the other card is in the subtree of this card, like 123a1b is
+    /// in the subtree of 123a. A card is not its own ancestor.
+    pub fn is_ancestor_of(&self, that: &Face) -> bool {
+        let n = self.name_components.len();
+        return n < that.name_components.len() && self.name_components[..] == that.name_components[..n];
     }
 
-    pub fn name(&self) -> String {
-        let mut name = String::new();
-        for comp in &self.name_components {
-            let s = match comp {
-                Component::Number(number) => number.to_string(),
-                Component::Char(chars) => String::from(chars),
+    /// The name of this card when the card `from` and everything below it is
+    /// moved to `to`: with 123a moved to 45c, 123a1b becomes 45c1b. The levels
+    /// below keep their positions but turn from numbers to letters and back
+    /// if needed: with 123a moved to 45, 123a1b becomes 45a2. None if this
+    /// card is not `from` or below it.
+    pub fn rebase(&self, from: &Face, to: &Face) -> Option<Face> {
+        if self != from && !from.is_ancestor_of(self) {
+            return None;
+        }
+
+        let mut name_components = to.name_components.clone();
+        for component in &self.name_components[from.name_components.len()..] {
+            let index = match component {
+                Component::Number(number) => *number,
+                Component::Char(chars) => letters_index(chars),
             };
-            name.push_str(s.as_str());
+            let next = match to.scheme.first_child(&name_components) {
+                Component::Number(_) => Component::Number(index),
+                Component::Char(_) => Component::Char(index_letters(index)),
+            };
+            name_components.push(next);
         }
+        return Some(Face { name_components, scheme: to.scheme });
+    }
 
-        return name;
+    /// The card that continues the same train of thought on the same level:
+    /// 123 -> 124, 123a -> 123b, 123a1 -> 123a2 and 123z -> 123aa.
+    pub fn next_sibling(&self) -> Face {
+        let mut name_components = self.name_components.clone();
+        let last = name_components.pop().unwrap();
+        let next = match last {
+            Component::Number(number) => Component::Number(number + 1),
+            Component::Char(chars) => Component::Char(next_letters(&chars)),
+        };
+        name_components.push(next);
+        return Face { name_components, scheme: self.scheme };
     }
 
-    pub fn is_major(&self) -> bool {
-        return self.name_components.len() == 1;
+    /// The first card that branches off this one: 123 -> 123a, 123a -> 123a1.
+    pub fn first_child(&self) -> Face {
+        let mut name_components = self.name_components.clone();
+        name_components.push(self.scheme.first_child(&name_components));
+        return Face { name_components, scheme: self.scheme };
     }
 
-    pub fn location_in(&self, dir: &Path) -> PathBuf {
-        let mut file = PathBuf::from(dir);
-        file.push(self.name());
-        return file;
+    /// Folder next to the card file where the attachments of the card are written.
+    pub fn attachments_in(&self, dir: &Path, config: &CardConfig) -> PathBuf {
+        let mut folder = config.layout.folder_of(dir, self);
+        folder.push(format!("{}.attachments", self.file_name()));
+        return folder;
     }
 }
 
 
 /* Ordering implementations */
 
-impl PartialEq for Component {
-    fn eq(&self, that: &Component) -> bool {
+// Cards are ordered component by component: numbers by value and letters in
+// the order of next_letters(..), so 123z < 123aa. A card comes before the
+// cards below it: 123a < 123a1 < 123b. Numbers come before letters on the
+// same level like in 21/3d7,1 < 21/3d7a of NamingScheme::Luhmann.
+
+impl Ord for Component {
+    fn cmp(&self, that: &Component) -> Ordering {
         match (self, that) {
-            (Component::Number(n), Component::Number(m)) => n == m,
-            (Component::Char(n), Component::Char(m)) => n == m,
-            _ => {
-                panic!("Compare numbers to chars");
-            }
+            (Component::Number(n), Component::Number(m)) => n.cmp(m),
+            (Component::Char(n), Component::Char(m)) => n.len().cmp(&m.len()).then(n.cmp(m)),
+            (Component::Number(_), Component::Char(_)) => Ordering::Less,
+            (Component::Char(_), Component::Number(_)) => Ordering::Greater,
         }
     }
 }
 
-impl Eq for Component { }
-
 impl PartialOrd for Component {
     fn partial_cmp(&self, that: &Component) -> Option<Ordering> {
-        match (self, that) {
-            (Component::Number(n), Component::Number(m)) => n.partial_cmp(m),
-            (Component::Char(n), Component::Char(m)) => n.partial_cmp(m),
-            _ => None
-        }
+        Some(self.cmp(that))
     }
 }
 
-impl PartialEq for Face {
-    fn eq(&self, that: &Face) -> bool {
+impl PartialEq for Component {
+    fn eq(&self, that: &Component) -> bool {
         self.cmp(that) == Ordering::Equal
     }
 }
 
-impl Eq for Face { }
+impl Eq for Component { }
+
+impl Ord for Face {
+    fn cmp(&self, that: &Face) -> Ordering {
+        return self.scheme.order(&self.name_components, &that.name_components);
+    }
+}
 
 impl PartialOrd for Face {
     fn partial_cmp(&self, that: &Face) -> Option<Ordering> {
-        let both = self.name_components.iter().zip(&that.name_components);
-
-        for (this, that) in both {
-            let comp_order = this.partial_cmp(that);
-            if let Some(Ordering::Equal) = comp_order {
-                return Some(Ordering::Equal);
-            }
-            return comp_order;
-        }
-
-        // All components were the same until now. If there's more components left
-        // in either list, that's a deeper sub-card and therefore greater.
-        return self.name_components.len().partial_cmp(&that.name_components.len());
+        Some(self.cmp(that))
     }
 }
 
-impl Ord for Face {
-    fn cmp(&self, that: &Face) -> Ordering {
-        // Valid cards can be always compared. Component has only PartialOrd because
-        // Numbers cannot be compared against Chars. A valid cards have Numbers and
-        // Chars in alternating order so invalid comparison cannot happen.
-        return self.partial_cmp(that).unwrap();
+impl PartialEq for Face {
+    fn eq(&self, that: &Face) -> bool {
+        self.cmp(that) == Ordering::Equal
     }
 }
 
+impl Eq for Face { }
+
 
 #[cfg(test)]
 mod test {
@@ -198,7 +248,7 @@ mod test {
     fn test_create() {
         let cards = ["123", "123a", "123a1", "123a1b", "123a1b2"];
         for name in &cards {
-            let card = Face::from_name(&name).unwrap();
+            let card = NamingScheme::Alphanumeric.parse(name).unwrap();
             assert_eq!(card.major_number(), 123usize);
             assert_eq!(card.name(), *name);
         }
@@ -209,7 +259,7 @@ mod test {
         let numbers = [1usize, 123, 4_usize.pow(31)];
         for number in numbers.iter() {
             let name = number.to_string();
-            let card = Face::from_name(name.as_str()).unwrap();
+            let card = NamingScheme::Alphanumeric.parse(name.as_str()).unwrap();
             assert_eq!(card.major_number(), *number);
         }
     }
@@ -218,15 +268,132 @@ mod test {
     fn test_negative() {
         let cards = ["", "a123", "a123a1", "123A", "123?"];
         for name in &cards {
-            let card = Face::from_name(&name);
+            let card = NamingScheme::Alphanumeric.parse(name);
             assert!(card.is_none());
         }
     }
 
+    #[test]
+    fn test_next_sibling_and_first_child() {
+        let pairs = [("123", "124"), ("123a", "123b"), ("123a1", "123a2"), ("123z", "123aa"), ("1az", "1ba"), ("1zz", "1aaa")];
+        for (name, next) in pairs.iter() {
+            assert_eq!(NamingScheme::Alphanumeric.parse(name).unwrap().next_sibling().name(), *next);
+        }
+        let pairs = [("123", "123a"), ("123a", "123a1"), ("123a1", "123a1a")];
+        for (name, child) in pairs.iter() {
+            assert_eq!(NamingScheme::Alphanumeric.parse(name).unwrap().first_child().name(), *child);
+        }
+    }
+
+    #[test]
+    fn test_navigation() {
+        let card = NamingScheme::Alphanumeric.parse("123a1").unwrap();
+        assert_eq!(card.parent().unwrap().name(), "123a");
+        assert_eq!(card.parent().unwrap().parent().unwrap().name(), "123");
+        assert!(NamingScheme::Alphanumeric.parse("123").unwrap().parent().is_none());
+
+        let index = |name: &str| NamingScheme::Alphanumeric.parse(name).unwrap().sibling_index();
+        assert_eq!((index("123a"), index("123z"), index("123aa"), index("123a7")), (1, 26, 27, 7));
+
+        let ancestor = NamingScheme::Alphanumeric.parse("123a").unwrap();
+        assert!(ancestor.is_ancestor_of(&NamingScheme::Alphanumeric.parse("123a1").unwrap()));
+        assert!(ancestor.is_ancestor_of(&NamingScheme::Alphanumeric.parse("123a1b").unwrap()));
+        assert!(!ancestor.is_ancestor_of(&ancestor));
+        assert!(!ancestor.is_ancestor_of(&NamingScheme::Alphanumeric.parse("123b1").unwrap()));
+        assert!(!ancestor.is_ancestor_of(&NamingScheme::Alphanumeric.parse("123").unwrap()));
+    }
+
+    #[test]
+    fn test_rebase() {
+        let face = |name: &str| NamingScheme::Alphanumeric.parse(name).unwrap();
+        let rebase = |name: &str, from: &str, to: &str| face(name).rebase(&face(from), &face(to)).map(|card| card.name());
+        assert_eq!(rebase("123a", "123a", "45c"), Some(String::from("45c")));
+        assert_eq!(rebase("123a1b", "123a", "45c"), Some(String::from("45c1b")));
+        assert_eq!(rebase("123a1b", "123a", "45"), Some(String::from("45a2")));
+        assert_eq!(rebase("123a27", "123a", "7"), Some(String::from("7aa")));
+        assert_eq!(rebase("123", "123", "9b"), Some(String::from("9b")));
+        assert_eq!(rebase("123b", "123a", "45c"), None);
+        assert_eq!(rebase("123", "123a", "45c"), None);
+        for index in 1..1000 {
+            assert_eq!(letters_index(&index_letters(index)), index);
+        }
+    }
+
+    /// Cards 1..3 with up to three levels and sibling indices 1, 2, 26 and 27.
+    fn sample_cards() -> Vec<Face> {
+        let mut cards: Vec<Face> = (1..=3).map(|n| NamingScheme::Alphanumeric.parse(&n.to_string()).unwrap()).collect();
+        let mut level = cards.clone();
+        for _ in 0..3 {
+            let mut next = Vec::new();
+            for card in &level {
+                let mut child = card.first_child();
+                for index in 1..=27 {
+                    if [1, 2, 26, 27].contains(&index) {
+                        next.push(child.clone());
+                    }
+                    child = child.next_sibling();
+                }
+            }
+            cards.extend(next.iter().cloned());
+            level = next;
+        }
+        return cards;
+    }
+
+    #[test]
+    fn test_order() {
+        let face = |name: &str| NamingScheme::Alphanumeric.parse(name).unwrap();
+        let sorted = ["1", "1a", "1a1", "1a2", "1a10", "1b", "1z", "1aa", "1ab", "1ba", "1zz", "1aaa", "2", "10", "123", "123a"];
+        for pair in sorted.windows(2) {
+            assert!(face(pair[0]) < face(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+        assert!(face("123a") != face("123b"));
+        assert_eq!(face("123a"), face("123a"));
+        assert_eq!(face("0123a"), face("123a"));
+    }
+
+    #[test]
+    fn test_order_properties() {
+        let cards = sample_cards();
+        for a in &cards {
+            assert_eq!(a.cmp(a), Ordering::Equal);
+            assert!(*a < a.next_sibling());
+            assert!(*a < a.first_child());
+            assert!(a.first_child() < a.next_sibling());
+            if let Some(parent) = a.parent() {
+                assert!(parent < *a);
+                assert!(*a < parent.next_sibling());
+            }
+            for b in &cards {
+                // Antisymmetric and consistent with the names.
+                assert_eq!(a.cmp(b), b.cmp(a).reverse());
+                assert_eq!(a == b, a.name() == b.name());
+                assert_eq!(a.partial_cmp(b), Some(a.cmp(b)));
+            }
+        }
+
+        // Sorting is a total order: sorted cards are strictly increasing and
+        // the order does not depend on the order of the input.
+        let mut sorted = cards.clone();
+        sorted.sort();
+        let mut reversed = cards.clone();
+        reversed.reverse();
+        reversed.sort();
+        assert_eq!(sorted, reversed);
+        for pair in sorted.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+        for (i, a) in sorted.iter().enumerate() {
+            for b in &sorted[i + 1..] {
+                assert!(a < b);
+            }
+        }
+    }
+
     #[test]
     fn test_location_in() {
-        let card = Face::from_name("123a").unwrap();
+        let card = NamingScheme::Alphanumeric.parse("123a").unwrap();
         let dir = PathBuf::from("./foo/bar");
-        assert_eq!(card.location_in(&dir), PathBuf::from("./foo/bar/124a"));
+        assert_eq!(card.location_in(&dir, &CardConfig::default()), PathBuf::from("./foo/bar/123a"));
     }
 }
@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+use super::face::Face;
+
+/// Where the card files are in the card folder. A flat folder has every card
+/// file in it. Very large slip boxes can spread the cards to subfolders by
+/// ranges of major numbers like 0-999/123a and 1000-1999/1024b. It is set in
+/// the configuration of the timeline. See `zk relayout`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FolderLayout {
+    #[default]
+    Flat,
+    /// Subfolders of this many major numbers.
+    Ranges(usize),
+}
+
+impl FolderLayout {
+    /// Name in the folder_layout column. The flat layout is stored as null.
+    pub fn name(&self) -> Option<String> {
+        match self {
+            FolderLayout::Flat => None,
+            FolderLayout::Ranges(size) => Some(format!("ranges:{}", size)),
+        }
+    }
+
+    pub fn from_name(name: Option<&str>) -> Result<FolderLayout, String> {
+        match name {
+            None | Some("flat") => Ok(FolderLayout::Flat),
+            Some(other) => {
+                let size = other.strip_prefix("ranges:").and_then(|size| size.parse::<usize>().ok());
+                match size {
+                    Some(size) if size > 0 => Ok(FolderLayout::Ranges(size)),
+                    _ => Err(format!("Unknown folder layout: {}. Use flat or ranges:<size>", other)),
+                }
+            }
+        }
+    }
+
+    pub fn label(&self) -> String {
+        self.name().unwrap_or_else(|| String::from("flat"))
+    }
+
+    /// Subfolder of the card relative to the card folder. Empty in the flat
+    /// layout.
+    pub fn subfolder(&self, card: &Face) -> PathBuf {
+        match self {
+            FolderLayout::Flat => PathBuf::new(),
+            FolderLayout::Ranges(size) => {
+                let start = card.major_number() / size * size;
+                PathBuf::from(format!("{}-{}", start, start + size - 1))
+            },
+        }
+    }
+
+    /// Folder of the card file and the attachments of the card.
+    pub fn folder_of(&self, dir: &Path, card: &Face) -> PathBuf {
+        return dir.join(self.subfolder(card));
+    }
+
+    /// Whether the name is the name of a subfolder in this layout.
+    pub fn is_subfolder(&self, name: &str) -> bool {
+        match self {
+            FolderLayout::Flat => false,
+            FolderLayout::Ranges(size) => {
+                let range = name.split_once('-').and_then(|(start, end)| Some((start.parse::<usize>().ok()?, end.parse::<usize>().ok()?)));
+                match range {
+                    Some((start, end)) => start % size == 0 && end == start + size - 1 && name == format!("{}-{}", start, end),
+                    None => false,
+                }
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::naming::NamingScheme;
+
+    #[test]
+    fn test_ranges() {
+        let card = |name: &str| NamingScheme::Alphanumeric.parse(name).unwrap();
+        let layout = FolderLayout::Ranges(1000);
+        assert_eq!(layout.subfolder(&card("123a")), PathBuf::from("0-999"));
+        assert_eq!(layout.subfolder(&card("1000")), PathBuf::from("1000-1999"));
+        assert_eq!(layout.folder_of(Path::new("cards"), &card("2999b1")), PathBuf::from("cards/2000-2999"));
+        assert_eq!(FolderLayout::Flat.folder_of(Path::new("cards"), &card("123a")), PathBuf::from("cards"));
+
+        assert!(layout.is_subfolder("0-999") && layout.is_subfolder("5000-5999"));
+        for name in ["0-99", "1-1000", "00-999", "0-999.attachments", "abc"].iter() {
+            assert!(!layout.is_subfolder(name), "{}", name);
+        }
+        assert!(!FolderLayout::Flat.is_subfolder("0-999"));
+
+        for layout in [FolderLayout::Flat, FolderLayout::Ranges(100)].iter() {
+            assert_eq!(FolderLayout::from_name(layout.name().as_deref()), Ok(*layout));
+        }
+        assert!(FolderLayout::from_name(Some("ranges:0")).is_err());
+    }
+}
@@ -1,13 +1,17 @@
 use super::Face;
+use std::fmt;
 use std::ops::Deref;
-use std::cmp::Ordering;
+use std::time::SystemTime;
+use chrono::{DateTime, NaiveDate, SecondsFormat, TimeZone, Utc};
 
 pub struct Meta {
     pub face: Face,
     pub create_time: Timestamp,
     pub modify_time: Timestamp,
     pub content_sha256: String,
+    #[allow(dead_code)]
     pub commit_user: String,
+    #[allow(dead_code)]
     pub commit_email: String,
 }
 
@@ -19,22 +23,75 @@ impl Deref for Meta {
 }
 
 
-// Note(wistrandj): Later, add the create and modify datetimes in the
-// sqlite3 timeline file. Until now, use an dummy implementation.
-pub struct Timestamp { }
-impl PartialEq for Timestamp {
-    fn eq(&self, other: &Timestamp) -> bool {
-        // Note(wistrandj): Everything are the same!
-        return true;
-    }
+/// An instant in UTC. Stored in the timeline file as RFC 3339 text with
+/// second precision, e.g. "2026-10-18T14:32:00Z", which sorts the same way
+/// as the instants themselves.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    instant: DateTime<Utc>,
 }
 
-impl Eq for Timestamp {}
+impl Timestamp {
+    pub fn now() -> Timestamp {
+        Self::from_system_time(SystemTime::now())
+    }
+
+    /// The Unix epoch. Used for cards saved before timestamps were recorded.
+    pub fn unknown() -> Timestamp {
+        Timestamp { instant: Utc.timestamp(0, 0) }
+    }
+
+    pub fn from_system_time(time: SystemTime) -> Timestamp {
+        let instant: DateTime<Utc> = DateTime::from(time);
+        Timestamp { instant: Utc.timestamp(instant.timestamp(), 0) }
+    }
+
+    /// Parse either a RFC 3339 time or a plain date "2026-09-01", which
+    /// means the midnight of that day in UTC.
+    pub fn parse(text: &str) -> Option<Timestamp> {
+        if let Ok(instant) = DateTime::parse_from_rfc3339(text) {
+            let instant: DateTime<Utc> = instant.with_timezone(&Utc);
+            return Some(Timestamp { instant: Utc.timestamp(instant.timestamp(), 0) });
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+            let instant = Utc.from_utc_datetime(&date.and_hms(0, 0, 0));
+            return Some(Timestamp { instant });
+        }
+        return None;
+    }
+
+    /// Same as parse(..) but fall back to unknown() on missing or invalid text.
+    pub fn parse_or_unknown(text: Option<String>) -> Timestamp {
+        if let Some(text) = text {
+            if let Some(timestamp) = Self::parse(&text) {
+                return timestamp;
+            }
+        }
+        return Self::unknown();
+    }
+}
 
-impl PartialOrd for Timestamp {
-    fn partial_cmp(&self, other: &Timestamp) -> Option<Ordering> {
-        return Some(Ordering::Equal);
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.instant.to_rfc3339_opts(SecondsFormat::Secs, true))
     }
 }
 
 
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let date = Timestamp::parse("2026-09-01").unwrap();
+        assert_eq!(date.to_string(), "2026-09-01T00:00:00Z");
+
+        let time = Timestamp::parse("2026-09-01T12:30:15+02:00").unwrap();
+        assert_eq!(time.to_string(), "2026-09-01T10:30:15Z");
+        assert!(date < time);
+
+        assert!(Timestamp::parse("yesterday").is_none());
+        assert_eq!(Timestamp::parse_or_unknown(None), Timestamp::unknown());
+    }
+}
@@ -1,5 +1,10 @@
+pub mod config;
 pub mod face;
+pub mod layout;
 pub mod meta;
+pub mod naming;
+pub mod pattern;
+pub mod tree;
 
 pub use self::face::*;
 pub use self::meta::*;
@@ -0,0 +1,297 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use std::cmp::Ordering;
+use super::face::{Component, Face};
+
+/// How the cards of a timeline are named. The scheme parses and formats the
+/// names, orders the cards and picks the name of the next major card. It is
+/// set in the configuration of the timeline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NamingScheme {
+    /// Numbers and letters in turns like 123a1b. The default.
+    #[default]
+    Alphanumeric,
+    /// Luhmann's notation like 21/3d7a6. The section 21 is the major card and
+    /// the first level below it is a number after a slash. A comma separates
+    /// two numbers like in 21/3d7,1. A slash is an underscore in file names.
+    Luhmann,
+    /// The minute in UTC the major card was created like 202610181432,
+    /// followed by numbers and letters in turns.
+    Timestamp,
+    /// Major numbers with leading zeros to the given width like 0042a1.
+    Padded(usize),
+}
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M";
+
+/// Split the name to numbers and letters: 123a1b -> 123, a, 1, b. None if the
+/// name is not a number followed by numbers and letters.
+fn alphanumeric_components(name: &str) -> Option<Vec<Component>> {
+    if !name.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut result: Vec<Component> = Vec::new();
+    let mut rest = name;
+    while !rest.is_empty() {
+        let digits = take_while(rest, |c| c.is_ascii_digit());
+        if !digits.is_empty() {
+            result.push(Component::Number(digits.parse().ok()?));
+            rest = &rest[digits.len()..];
+            continue;
+        }
+        let letters = take_while(rest, |c| c.is_ascii_lowercase());
+        if letters.is_empty() {
+            return None;
+        }
+        result.push(Component::Char(String::from(letters)));
+        rest = &rest[letters.len()..];
+    }
+    return Some(result);
+}
+
+/// Split the name in Luhmann's notation: 21/3d7,1 -> 21, 3, d, 7, 1.
+fn luhmann_components(name: &str) -> Option<Vec<Component>> {
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut result: Vec<Component> = Vec::new();
+    let mut rest = name;
+    while !rest.is_empty() {
+        let previous_is_number = matches!(result.last(), Some(Component::Number(_)));
+        let (separator, allowed) = if rest.starts_with('/') {
+            (1, result.len() == 1)
+        } else if rest.starts_with(',') {
+            (1, result.len() > 1 && previous_is_number)
+        } else if rest.starts_with(|c: char| c.is_ascii_lowercase()) {
+            (0, result.len() > 1 && previous_is_number)
+        } else {
+            (0, !previous_is_number)
+        };
+        if !allowed {
+            return None;
+        }
+        rest = &rest[separator..];
+
+        let digits = take_while(rest, |c| c.is_ascii_digit());
+        let letters = take_while(rest, |c| c.is_ascii_lowercase());
+        if !digits.is_empty() {
+            result.push(Component::Number(digits.parse().ok()?));
+            rest = &rest[digits.len()..];
+        } else if !letters.is_empty() && separator == 0 {
+            result.push(Component::Char(String::from(letters)));
+            rest = &rest[letters.len()..];
+        } else {
+            return None;
+        }
+    }
+    return Some(result);
+}
+
+fn take_while(text: &str, predicate: fn(char) -> bool) -> &str {
+    let end = text.find(|c: char| !predicate(c)).unwrap_or(text.len());
+    return &text[..end];
+}
+
+impl NamingScheme {
+    /// Name in the naming_scheme column. The default is stored as null.
+    pub fn name(&self) -> Option<String> {
+        match self {
+            NamingScheme::Alphanumeric => None,
+            NamingScheme::Luhmann => Some(String::from("luhmann")),
+            NamingScheme::Timestamp => Some(String::from("timestamp")),
+            NamingScheme::Padded(width) => Some(format!("padded:{}", width)),
+        }
+    }
+
+    pub fn from_name(name: Option<&str>) -> Result<NamingScheme, String> {
+        match name {
+            None | Some("alphanumeric") => Ok(NamingScheme::Alphanumeric),
+            Some("luhmann") => Ok(NamingScheme::Luhmann),
+            Some("timestamp") => Ok(NamingScheme::Timestamp),
+            Some(other) => {
+                let width = other.strip_prefix("padded:").and_then(|width| width.parse::<usize>().ok());
+                match width {
+                    Some(width) if width > 0 => Ok(NamingScheme::Padded(width)),
+                    _ => Err(format!("Unknown naming scheme: {}. Use alphanumeric, luhmann, timestamp or padded:<width>", other)),
+                }
+            }
+        }
+    }
+
+    pub fn label(&self) -> String {
+        self.name().unwrap_or_else(|| String::from("alphanumeric"))
+    }
+
+    /// The card of the name. None if the name is not valid in this scheme.
+    pub fn parse(&self, name: &str) -> Option<Face> {
+        let name_components = match self {
+            NamingScheme::Luhmann => luhmann_components(name)?,
+            _ => alphanumeric_components(name)?,
+        };
+
+        if let NamingScheme::Timestamp = self {
+            let major = take_while(name, |c| c.is_ascii_digit());
+            if major.len() != 12 {
+                return None;
+            }
+            NaiveDateTime::parse_from_str(major, TIMESTAMP_FORMAT).ok()?;
+        }
+
+        // Note(wistrandj): The default scheme has always accepted leading
+        // zeros like 0123a1. The others have exactly one way to write a name.
+        let face = Face::from_components(name_components, *self);
+        if *self != NamingScheme::Alphanumeric && face.name() != name {
+            return None;
+        }
+        return Some(face);
+    }
+
+    pub(super) fn format(&self, name_components: &[Component]) -> String {
+        let mut name = String::new();
+        for (i, component) in name_components.iter().enumerate() {
+            match component {
+                Component::Number(number) if i == 0 => match self {
+                    NamingScheme::Padded(width) => name.push_str(&format!("{:0width$}", number, width = width)),
+                    _ => name.push_str(&number.to_string()),
+                },
+                Component::Number(number) => {
+                    let previous_is_number = matches!(name_components[i - 1], Component::Number(_));
+                    if *self == NamingScheme::Luhmann && previous_is_number {
+                        name.push(if i == 1 { '/' } else { ',' });
+                    }
+                    name.push_str(&number.to_string());
+                },
+                Component::Char(chars) => name.push_str(chars),
+            }
+        }
+        return name;
+    }
+
+    /// Name of the card file. Same as the card name unless the name has
+    /// characters that cannot be in a file name.
+    pub fn file_name(&self, name: &str) -> String {
+        match self {
+            NamingScheme::Luhmann => name.replace('/', "_"),
+            _ => String::from(name),
+        }
+    }
+
+    /// Opposite of file_name(..). Only the file name of the card itself is
+    /// accepted: 0123a is not a card file although 0123a is card 123a. Two
+    /// files would otherwise be the same card.
+    pub fn parse_file_name(&self, file_name: &str) -> Option<Face> {
+        let card = match self {
+            NamingScheme::Luhmann if !file_name.contains('/') => self.parse(&file_name.replace('_', "/"))?,
+            NamingScheme::Luhmann => return None,
+            _ => self.parse(file_name)?,
+        };
+        if self.file_name(&card.name()) != file_name {
+            return None;
+        }
+        return Some(card);
+    }
+
+    /// The first level of a card below the given one.
+    pub(super) fn first_child(&self, name_components: &[Component]) -> Component {
+        match name_components.last().unwrap() {
+            Component::Number(_) if *self == NamingScheme::Luhmann && name_components.len() == 1 => Component::Number(1),
+            Component::Number(_) => Component::Char(String::from("a")),
+            Component::Char(_) => Component::Number(1),
+        }
+    }
+
+    pub(super) fn order(&self, this: &[Component], that: &[Component]) -> Ordering {
+        // Every scheme orders component by component. A shorter list that is
+        // a prefix of the other is less.
+        return this.cmp(that);
+    }
+
+    /// The major card after the given cards. A new timestamp is the current
+    /// minute, or the minute after the latest card if that is later.
+    pub fn next_major(&self, cards: &[Face]) -> Face {
+        let latest = cards.iter().map(|card| card.major_number()).max().unwrap_or(0);
+        let number = match self {
+            NamingScheme::Timestamp => {
+                let now = Utc::now().naive_utc();
+                let after_latest = NaiveDateTime::parse_from_str(&latest.to_string(), TIMESTAMP_FORMAT)
+                    .map(|time| time + Duration::minutes(1));
+                let time = match after_latest {
+                    Ok(time) if time > now => time,
+                    _ => now,
+                };
+                time.format(TIMESTAMP_FORMAT).to_string().parse().unwrap()
+            },
+            _ => latest + 1,
+        };
+        return Face::from_components(vec![Component::Number(number)], *self);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_schemes() {
+        let valid = [
+            (NamingScheme::Alphanumeric, vec!["1", "123a1b", "123aa10"]),
+            (NamingScheme::Luhmann, vec!["21", "21/3", "21/3d7a6", "21/3d7,1", "21/3d7,1a"]),
+            (NamingScheme::Timestamp, vec!["202610181432", "202610181432a1"]),
+            (NamingScheme::Padded(4), vec!["0042", "0042a1", "12345"]),
+        ];
+        for (scheme, names) in valid.iter() {
+            for name in names {
+                let card = scheme.parse(name).unwrap_or_else(|| panic!("{} in {:?}", name, scheme));
+                assert_eq!(card.name(), *name);
+                assert_eq!(scheme.parse_file_name(&scheme.file_name(name)).unwrap(), card);
+                assert_eq!(NamingScheme::from_name(scheme.name().as_deref()), Ok(*scheme));
+            }
+        }
+
+        let invalid = [
+            (NamingScheme::Alphanumeric, vec!["", "a1", "21/3", "123A"]),
+            (NamingScheme::Luhmann, vec!["", "21a", "21/", "/3", "21/3/4", "21,3", "21/3d,1", "21/03"]),
+            (NamingScheme::Timestamp, vec!["123", "202613181432", "20261018143", "202610181432a01"]),
+            (NamingScheme::Padded(4), vec!["42", "00042", "0042a01"]),
+        ];
+        for (scheme, names) in invalid.iter() {
+            for name in names {
+                assert!(scheme.parse(name).is_none(), "{} in {:?}", name, scheme);
+            }
+        }
+        assert!(NamingScheme::from_name(Some("padded:0")).is_err());
+
+        // A name with leading zeros finds the card but is not its file.
+        assert_eq!(NamingScheme::Alphanumeric.parse("0123a").unwrap().name(), "123a");
+        assert!(NamingScheme::Alphanumeric.parse_file_name("0123a").is_none());
+        assert!(NamingScheme::Alphanumeric.parse_file_name("123a").is_some());
+    }
+
+    #[test]
+    fn test_navigation() {
+        let scheme = NamingScheme::Luhmann;
+        let card = |name: &str| scheme.parse(name).unwrap();
+        assert_eq!(card("21").first_child().name(), "21/1");
+        assert_eq!(card("21/3").first_child().name(), "21/3a");
+        assert_eq!(card("21/3d7").next_sibling().name(), "21/3d8");
+        assert_eq!(card("21/3d").parent().unwrap().name(), "21/3");
+        assert!(card("21/3") < card("21/3a"));
+        assert!(card("21/3z") < card("21/4"));
+        assert_eq!(card("21/3a1").rebase(&card("21/3"), &card("7/1")).unwrap().name(), "7/1a1");
+
+        let padded = NamingScheme::Padded(3);
+        assert_eq!(padded.next_major(&[padded.parse("009a").unwrap()]).name(), "010");
+        assert_eq!(padded.next_major(&[]).name(), "001");
+    }
+
+    #[test]
+    fn test_next_timestamp() {
+        let scheme = NamingScheme::Timestamp;
+        let future = scheme.parse("299912312359").unwrap();
+        assert_eq!(scheme.next_major(&[future]).name(), "300001010000");
+        let now = scheme.next_major(&[]);
+        assert!(scheme.parse(&now.name()).is_some());
+    }
+}
@@ -0,0 +1,249 @@
+use super::face::Face;
+use super::naming::NamingScheme;
+use std::fmt;
+
+/// How the card files are named in the card folder, like `{name}.md` or
+/// `{name}-{slug}.md`. The name is the card name in the naming scheme and the
+/// slug is made of the title of the card. The slug is left out with the text
+/// between it and the name when there is no title: `{name}-{slug}.md` names
+/// a new card 123a.md. It is set in the configuration of the timeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FilePattern {
+    tokens: Vec<Token>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Text(String),
+    Name,
+    Slug,
+}
+
+/// Longest slug in characters.
+const SLUG_LENGTH: usize = 50;
+
+/// First line of the card that has some text.
+pub fn title_of(text: &str) -> String {
+    let line = text.lines().map(|line| line.trim()).find(|line| line.len() > 0).unwrap_or("");
+    return String::from(line.trim_start_matches('#').trim());
+}
+
+/// The title in lower case letters and digits separated by dashes:
+/// "# Notes on Luhmann's box" -> notes-on-luhmann-s-box.
+pub fn slug_of(text: &str) -> String {
+    let mut slug = String::new();
+    for c in title_of(text).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(SLUG_LENGTH);
+    return String::from(slug.trim_end_matches('-'));
+}
+
+fn is_slug(text: &str) -> bool {
+    return !text.is_empty() && text.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+}
+
+impl Default for FilePattern {
+    /// The card file is named as the card. Stored as null.
+    fn default() -> FilePattern {
+        FilePattern { tokens: vec![Token::Name] }
+    }
+}
+
+impl FilePattern {
+    /// Pattern in the file_pattern column. The default is stored as null.
+    pub fn name(&self) -> Option<String> {
+        if *self == FilePattern::default() {
+            return None;
+        }
+        return Some(self.to_string());
+    }
+
+    pub fn from_name(name: Option<&str>) -> Result<FilePattern, String> {
+        let name = match name {
+            None => return Ok(FilePattern::default()),
+            Some(name) => name,
+        };
+
+        let mut tokens = Vec::new();
+        let mut rest = name;
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("{name}") {
+                tokens.push(Token::Name);
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("{slug}") {
+                tokens.push(Token::Slug);
+                rest = after;
+            } else {
+                let end = rest.char_indices().skip(1).find(|(_, c)| *c == '{').map(|(end, _)| end).unwrap_or(rest.len());
+                tokens.push(Token::Text(String::from(&rest[..end])));
+                rest = &rest[end..];
+            }
+        }
+
+        let count = |token: &Token| tokens.iter().filter(|it| *it == token).count();
+        let texts_valid = tokens.iter().all(|token| match token {
+            Token::Text(text) => !text.contains(|c| "{}/\\".contains(c)) && !text.starts_with('~'),
+            _ => true,
+        });
+        // Nothing tells apart the name and the slug if they are next to each other.
+        let adjacent = tokens.windows(2).any(|pair| pair.iter().all(|token| !matches!(token, Token::Text(_))));
+        let hidden = matches!(tokens.first(), Some(Token::Text(text)) if text.starts_with('.'));
+        if count(&Token::Name) != 1 || count(&Token::Slug) > 1 || !texts_valid || adjacent || hidden {
+            return Err(format!("Invalid file pattern: {}. Use {{name}} once and {{slug}} at most once, like {{name}}-{{slug}}.md", name));
+        }
+        return Ok(FilePattern { tokens });
+    }
+
+    pub fn has_slug(&self) -> bool {
+        return self.tokens.contains(&Token::Slug);
+    }
+
+    /// The tokens without the slug and the text between it and the name.
+    fn without_slug(&self) -> Vec<Token> {
+        let mut tokens = self.tokens.clone();
+        if let Some(i) = tokens.iter().position(|token| *token == Token::Slug) {
+            tokens.remove(i);
+            // The text between the name and the slug: "-" in {name}-{slug}.md
+            // and " " in {slug} {name}.md.
+            if i > 0 && matches!(tokens[i - 1], Token::Text(_)) && tokens[..i - 1].contains(&Token::Name) {
+                tokens.remove(i - 1);
+            } else if i < tokens.len() && matches!(tokens[i], Token::Text(_)) && tokens[i + 1..].contains(&Token::Name) {
+                tokens.remove(i);
+            }
+        }
+        return tokens;
+    }
+
+    /// File name of the card. An empty slug leaves out the slug.
+    pub fn file_name(&self, card: &Face, slug: &str) -> String {
+        let tokens = if slug.is_empty() { self.without_slug() } else { self.tokens.clone() };
+        let mut file_name = String::new();
+        for token in &tokens {
+            match token {
+                Token::Text(text) => file_name.push_str(text),
+                Token::Name => file_name.push_str(&card.file_name()),
+                Token::Slug => file_name.push_str(slug),
+            }
+        }
+        return file_name;
+    }
+
+    /// The card and the slug of the file name. None if the file name does not
+    /// match the pattern.
+    pub fn parse(&self, scheme: &NamingScheme, file_name: &str) -> Option<(Face, String)> {
+        for tokens in [self.tokens.clone(), self.without_slug()].iter() {
+            let mut found = (None, String::new());
+            if match_tokens(tokens, file_name, scheme, &mut found) {
+                let (card, slug) = found;
+                return card.map(|card| (card, slug));
+            }
+        }
+        return None;
+    }
+}
+
+impl fmt::Display for FilePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in &self.tokens {
+            match token {
+                Token::Text(text) => write!(f, "{}", text)?,
+                Token::Name => write!(f, "{{name}}")?,
+                Token::Slug => write!(f, "{{slug}}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether the text matches the tokens. Try the longest names and slugs first.
+fn match_tokens(tokens: &[Token], text: &str, scheme: &NamingScheme, found: &mut (Option<Face>, String)) -> bool {
+    let ends = || (1..=text.len()).rev().filter(move |end| text.is_char_boundary(*end));
+    match tokens.first() {
+        None => text.is_empty(),
+        Some(Token::Text(prefix)) => {
+            text.starts_with(prefix.as_str()) && match_tokens(&tokens[1..], &text[prefix.len()..], scheme, found)
+        },
+        Some(Token::Name) => {
+            for end in ends() {
+                if let Some(card) = scheme.parse_file_name(&text[..end]) {
+                    if match_tokens(&tokens[1..], &text[end..], scheme, found) {
+                        found.0 = Some(card);
+                        return true;
+                    }
+                }
+            }
+            false
+        },
+        Some(Token::Slug) => {
+            for end in ends() {
+                if is_slug(&text[..end]) && match_tokens(&tokens[1..], &text[end..], scheme, found) {
+                    found.1 = String::from(&text[..end]);
+                    return true;
+                }
+            }
+            false
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_title_of() {
+        assert_eq!(title_of("\n\n# Zettelkasten  \nbody"), "Zettelkasten");
+        assert_eq!(title_of("2026-10-18\n\n\n"), "2026-10-18");
+        assert_eq!(title_of(""), "");
+        assert_eq!(slug_of("# Notes on Luhmann's box!\n"), "notes-on-luhmann-s-box");
+        assert_eq!(slug_of("## --\n"), "");
+        assert_eq!(slug_of(&"word ".repeat(20)).len(), 49);
+    }
+
+    #[test]
+    fn test_patterns() {
+        let scheme = NamingScheme::Alphanumeric;
+        let card = scheme.parse("123a1").unwrap();
+        let parse = |pattern: &FilePattern, file_name: &str| pattern.parse(&scheme, file_name)
+            .map(|(card, slug)| (card.name(), slug));
+
+        let plain = FilePattern::from_name(None).unwrap();
+        assert_eq!(plain.file_name(&card, "ignored"), "123a1");
+        assert_eq!(parse(&plain, "123a1"), Some((String::from("123a1"), String::new())));
+        assert_eq!(parse(&plain, "123a1.md"), None);
+
+        let markdown = FilePattern::from_name(Some("{name}.md")).unwrap();
+        assert_eq!(markdown.file_name(&card, ""), "123a1.md");
+        assert_eq!(parse(&markdown, "123a1.md"), Some((String::from("123a1"), String::new())));
+        assert_eq!(parse(&markdown, "123a1"), None);
+
+        let slugged = FilePattern::from_name(Some("{name}-{slug}.md")).unwrap();
+        assert_eq!(slugged.file_name(&card, "on-cards"), "123a1-on-cards.md");
+        assert_eq!(slugged.file_name(&card, ""), "123a1.md");
+        assert_eq!(parse(&slugged, "123a1-on-cards.md"), Some((String::from("123a1"), String::from("on-cards"))));
+        assert_eq!(parse(&slugged, "123a1-2026.md"), Some((String::from("123a1"), String::from("2026"))));
+        assert_eq!(parse(&slugged, "123a1.md"), Some((String::from("123a1"), String::new())));
+        assert_eq!(parse(&slugged, "123a1-.md"), None);
+        assert_eq!(parse(&slugged, "notes.md"), None);
+
+        let prefixed = FilePattern::from_name(Some("{slug} {name}.txt")).unwrap();
+        assert_eq!(prefixed.file_name(&card, "x"), "x 123a1.txt");
+        assert_eq!(prefixed.file_name(&card, ""), "123a1.txt");
+        assert_eq!(parse(&prefixed, "123a1.txt"), Some((String::from("123a1"), String::new())));
+        assert_eq!(parse(&prefixed, "a-b 123a1.txt"), Some((String::from("123a1"), String::from("a-b"))));
+
+        for name in [slugged.to_string(), markdown.to_string()].iter() {
+            assert_eq!(FilePattern::from_name(Some(name)).unwrap().to_string(), *name);
+        }
+        assert_eq!(FilePattern::from_name(Some("{name}")).unwrap().name(), None);
+        for invalid in ["", "card.md", "{name}{slug}", "{name}/{slug}", "{name}-{name}", "{name}.{md}", ".{name}", "~{name}"].iter() {
+            assert!(FilePattern::from_name(Some(invalid)).is_err(), "{}", invalid);
+        }
+    }
+}
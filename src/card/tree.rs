@@ -0,0 +1,105 @@
+use crate::card::Face;
+
+/// A card and the cards that branch off it.
+pub struct Node {
+    pub card: Face,
+    /// False for a card that is only implied by the names of its
+    /// descendants, like 123a when only 123a1 exists.
+    pub exists: bool,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    fn new(card: Face) -> Node {
+        Node { card, exists: false, children: Vec::new() }
+    }
+
+    /// Number of existing cards in the subtree, not counting this one.
+    pub fn descendants(&self) -> usize {
+        return self.children.iter()
+            .map(|child| child.descendants() + if child.exists { 1 } else { 0 })
+            .sum();
+    }
+
+    /// The node of the card in this subtree.
+    pub fn find(&self, card: &Face) -> Option<&Node> {
+        if self.card == *card {
+            return Some(self);
+        }
+        return self.children.iter().find_map(|child| child.find(card));
+    }
+
+    fn sort(&mut self) {
+        self.children.sort_by(|a, b| a.card.cmp(&b.card));
+        for child in self.children.iter_mut() {
+            child.sort();
+        }
+    }
+}
+
+/// Add the last card of the path under the cards before it in the path.
+fn insert(level: &mut Vec<Node>, path: &[Face]) {
+    let face = &path[0];
+    let position = level.iter().position(|it| it.card == *face);
+    let position = match position {
+        Some(position) => position,
+        None => {
+            level.push(Node::new(face.clone()));
+            level.len() - 1
+        },
+    };
+
+    let node = &mut level[position];
+    if path.len() == 1 {
+        node.exists = true;
+    } else {
+        insert(&mut node.children, &path[1..]);
+    }
+}
+
+/// The trees the card names imply, one for each major card.
+pub fn build(cards: &[Face]) -> Vec<Node> {
+    let mut roots: Vec<Node> = Vec::new();
+
+    for card in cards {
+        // From the major card down to the card itself.
+        let mut path = vec![card.clone()];
+        while let Some(parent) = path.last().unwrap().parent() {
+            path.push(parent);
+        }
+        path.reverse();
+        insert(&mut roots, &path);
+    }
+
+    roots.sort_by(|a, b| a.card.cmp(&b.card));
+    for root in roots.iter_mut() {
+        root.sort();
+    }
+    return roots;
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::card::naming::NamingScheme;
+
+    #[test]
+    fn test_build() {
+        let cards: Vec<Face> = ["1", "1a", "1a1", "1b2", "2"].iter()
+            .map(|name| NamingScheme::Alphanumeric.parse(name).unwrap())
+            .collect();
+        let roots = build(&cards);
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[0].descendants(), 3);
+        assert_eq!(roots[1].descendants(), 0);
+
+        // 1b exists only through its child.
+        let implied = roots[0].find(&NamingScheme::Alphanumeric.parse("1b").unwrap()).unwrap();
+        assert!(!implied.exists);
+        assert_eq!(implied.descendants(), 1);
+        assert_eq!(implied.children[0].card.name(), "1b2");
+        assert!(roots[0].find(&NamingScheme::Alphanumeric.parse("1a1").unwrap()).unwrap().exists);
+        assert!(roots[0].find(&NamingScheme::Alphanumeric.parse("3").unwrap()).is_none());
+    }
+}
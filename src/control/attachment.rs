@@ -0,0 +1,42 @@
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use crate::card::Face;
+use crate::model::{attachment, blob, carddb};
+
+/// A command to store a file and attach it to a card.
+pub struct AttachFile<'a> {
+    connection: &'a mut Connection,
+    card: Face,
+    file: PathBuf,
+    file_name: String,
+    mime_type: String,
+}
+
+impl<'a> AttachFile<'a> {
+    /// Attach the file under its own file name. Guess the mime type unless given.
+    pub fn new(connection: &'a mut Connection, folder: &Path, card: Face, file: &Path, mime_type: Option<&str>) -> Result<AttachFile<'a>, String> {
+        let in_timeline = carddb::card_hash(connection, &card).is_some();
+        if !in_timeline && !card.location_in(folder, &carddb::card_config(connection)).is_file() {
+            return Err(format!("The card {} does not exist", card.name()));
+        }
+        if !file.is_file() {
+            return Err(format!("Not a file: {}", file.to_string_lossy()));
+        }
+
+        let file_name = file.file_name().unwrap().to_string_lossy().to_string();
+        let mime_type = match mime_type {
+            Some(mime_type) => String::from(mime_type),
+            None => String::from(attachment::guess_mime_type(&file_name)),
+        };
+
+        Ok(AttachFile { connection, card, file: file.to_path_buf(), file_name, mime_type })
+    }
+
+    pub fn call_once(self) -> Result<(), String> {
+        let savepoint = self.connection.savepoint().map_err(|_| String::from("Fail to start a transaction"))?;
+        let hash = blob::save(&savepoint, &self.file)?;
+        attachment::attach(&savepoint, &self.card, &self.file_name, &self.mime_type, &hash.to_string())?;
+        savepoint.commit().map_err(|_| String::from("Fail to commit the transaction"))?;
+        Ok(())
+    }
+}
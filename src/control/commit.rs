@@ -0,0 +1,64 @@
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use crate::card::Meta;
+use crate::card::config::CardConfig;
+use crate::control::content;
+use crate::model::{blob, carddb, commit};
+
+/// A command to save every new or modified open card to the timeline as a
+/// single commit. Either all of the cards are saved or none of them.
+pub struct CommitModifiedCards<'a> {
+    connection: &'a mut Connection,
+    folder: PathBuf,
+    config: CardConfig,
+    message: String,
+    author: String,
+    cards: Vec<Meta>,
+}
+
+impl<'a> CommitModifiedCards<'a> {
+    pub fn new(connection: &'a mut Connection, folder: &Path, message: &str, author: &str) -> CommitModifiedCards<'a> {
+        let cards = content::modified_open_cards(folder, connection);
+        let config = carddb::card_config(connection);
+        CommitModifiedCards {
+            connection,
+            folder: folder.to_path_buf(),
+            config,
+            message: String::from(message),
+            author: String::from(author),
+            cards,
+        }
+    }
+
+    /// Cards that will be saved by this commit.
+    pub fn cards(&self) -> &Vec<Meta> {
+        &self.cards
+    }
+
+    /// Save the cards and return the id of the new commit.
+    pub fn call_once(self) -> Result<usize, &'static str> {
+        if self.cards.len() == 0 {
+            return Err("Nothing to commit");
+        }
+
+        let savepoint = self.connection.savepoint();
+        if savepoint.is_err() {
+            return Err("Fail to start a transaction");
+        }
+        let savepoint = savepoint.unwrap();
+
+        let commit_id = commit::create_commit(&savepoint, &self.message, &self.author)?;
+
+        for card in &self.cards {
+            let file = card.location_in(&self.folder, &self.config);
+            let hash = blob::save(&savepoint, &file)?;
+            carddb::save_card_and_hash(&savepoint, &card.face, &hash, commit_id)?;
+        }
+
+        // Dropping the savepoint without commit rolls back the changes made above.
+        match savepoint.commit() {
+            Ok(_) => Ok(commit_id),
+            Err(_) => Err("Fail to commit the transaction"),
+        }
+    }
+}
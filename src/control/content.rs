@@ -4,62 +4,261 @@
 use rusqlite::{Connection, params};
 use crate::card::{Timestamp, Meta};
 use crate::card::Face;
-use std::collections::HashSet;
+use crate::model::cardfolder::CardFolder;
+use crate::model::{blob, carddb, revision};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::fs;
 use crate::hash;
 
-fn all_timeline_cards(conn: &Connection) -> Vec<Meta> {
-    return vec![];
+/// State of a single card when the card folder is compared against the timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The card is in the card folder but not in the timeline.
+    New,
+    /// The card is in both but the content differs.
+    Modified,
+    /// The card is in the timeline but its file is missing from the card folder.
+    Deleted,
+    /// The card is in both with the same content.
+    Unchanged,
 }
 
-fn all_open_cards(folder: &Path) -> Vec<Meta> {
-    return vec![];
+impl Status {
+    /// A single letter code for the machine readable output.
+    pub fn code(&self) -> char {
+        match self {
+            Status::New => 'N',
+            Status::Modified => 'M',
+            Status::Deleted => 'D',
+            Status::Unchanged => 'U',
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Status::New => "new",
+            Status::Modified => "modified",
+            Status::Deleted => "deleted",
+            Status::Unchanged => "unchanged",
+        }
+    }
+}
+
+pub struct CardStatus {
+    pub status: Status,
+    /// Meta of the open card, or of the timeline card if the card is deleted.
+    pub card: Meta,
+}
+
+pub fn all_timeline_cards(conn: &Connection) -> Vec<Meta> {
+    let scheme = carddb::naming_scheme(conn);
+    let sql = "
+        select card.card_name, card.content_sha256, card.create_time, card.modify_time, commit_log.author
+        from card left join commit_log on card.commit_id = commit_log.commit_id;";
+    let mut stmt = conn.prepare(sql).unwrap();
+    let rows = stmt.query_map(
+        params![],
+        |row| {
+            let name: String = row.get(0)?;
+            let content_sha256: String = row.get(1)?;
+            let create_time: Option<String> = row.get(2)?;
+            let modify_time: Option<String> = row.get(3)?;
+            let author: Option<String> = row.get(4)?;
+            Ok((name, content_sha256, create_time, modify_time, author))
+        }).unwrap();
+
+    let mut cards = Vec::new();
+    for row in rows {
+        let (name, content_sha256, create_time, modify_time, author) = row.unwrap();
+        if let Some(face) = scheme.parse(&name) {
+            cards.push(Meta {
+                face,
+                create_time: Timestamp::parse_or_unknown(create_time),
+                modify_time: Timestamp::parse_or_unknown(modify_time),
+                content_sha256,
+                commit_user: author.unwrap_or_default(),
+                commit_email: String::new(),
+            });
+        } else {
+            eprintln!("Invalid card name in the timeline: {}", name);
+        }
+    }
+
+    return cards;
 }
 
-fn timestamp_of_file(file: &Path) -> Timestamp {
-    Timestamp { }
+/// The cards in the card folder hashed with the given algorithm.
+pub fn all_open_cards(opencards: &CardFolder, algorithm: hash::Algorithm) -> Vec<Meta> {
+    return open_cards_by(opencards, |_| algorithm);
+}
+
+/// The cards in the card folder each hashed with the algorithm given by its name.
+fn open_cards_by(opencards: &CardFolder, algorithm_of: impl Fn(&Face) -> hash::Algorithm) -> Vec<Meta> {
+    let mut cards = Vec::new();
+
+    for (face, file) in opencards.card_files() {
+        let hash = hash::Hash::file(&file, algorithm_of(&face));
+        if let Ok(hash) = hash {
+            let (create_time, modify_time) = timestamp_of_file(&file);
+            cards.push(Meta {
+                face,
+                create_time,
+                modify_time,
+                content_sha256: hash.to_string(),
+                commit_user: String::new(),
+                commit_email: String::new(),
+            });
+        } else {
+            eprintln!("Fail to read the card {}", file.to_string_lossy());
+        }
+    }
+
+    return cards;
+}
+
+/// Create and modify time of a file. Not every filesystem records the
+/// creation time so fall back to the modify time.
+fn timestamp_of_file(file: &Path) -> (Timestamp, Timestamp) {
+    let metadata = fs::metadata(file);
+    if metadata.is_err() {
+        return (Timestamp::unknown(), Timestamp::unknown());
+    }
+    let metadata = metadata.unwrap();
+
+    let modify_time = match metadata.modified() {
+        Ok(time) => Timestamp::from_system_time(time),
+        Err(_) => Timestamp::unknown(),
+    };
+    let create_time = match metadata.created() {
+        Ok(time) => Timestamp::from_system_time(time),
+        Err(_) => modify_time.clone(),
+    };
+
+    return (create_time, modify_time);
 }
 
 
-fn modified_open_cards(folder: &Path, conn: &Connection) -> Vec<Meta> {
-    // Note(wistrandj): I am not very proud of this piece of code.
-    let opencards: Vec<Meta> = all_open_cards(folder);
+/// The new and modified cards in the card folder in the card order.
+pub fn modified_open_cards(folder: &Path, conn: &Connection) -> Vec<Meta> {
+    return status(folder, conn).into_iter()
+        .filter(|card| card.status == Status::New || card.status == Status::Modified)
+        .map(|card| card.card)
+        .collect();
+}
+
+/// Compare every card in the card folder against the timeline.
+pub fn status(folder: &Path, conn: &Connection) -> Vec<CardStatus> {
     let timelinecards: Vec<Meta> = all_timeline_cards(conn);
 
-    // Error(wistrandj): Expect a named lifetime parameter.
-    // type Name = &str;
-    // type Content = &str;
+    let mut timeline_hashes: HashMap<String, String> = HashMap::new();
+    for card in &timelinecards {
+        timeline_hashes.insert(card.face.name(), card.content_sha256.clone());
+    }
+
+    // Note(wistrandj): A card is compared by the algorithm of its stored
+    // hash. The algorithm of the timeline may have changed after it was
+    // stored and the content is not rehashed until `zk blob rehash`.
+    let algorithm = carddb::hash_algorithm(conn);
+    let opencards: Vec<Meta> = open_cards_by(&CardFolder::open(folder, conn), |face| {
+        match timeline_hashes.get(&face.name()).map(|stored| hash::split_algorithm(stored)) {
+            Some(Ok((stored, _))) => stored,
+            _ => algorithm,
+        }
+    });
 
-    let mut opencards_set: HashSet<(String, String)> = HashSet::new();
-    let mut timelinecards_set: HashSet<(String, String)> = HashSet::new();
+    let mut open_names: HashSet<String> = HashSet::new();
+    let mut result = Vec::new();
 
     for card in opencards {
-        let face = card.face.name();
-        let sha256 = String::from(card.content_sha256);
-        opencards_set.insert((face, sha256));
+        let name = card.face.name();
+        let status = match timeline_hashes.get(&name) {
+            None => Status::New,
+            Some(sha256) if *sha256 == card.content_sha256 => Status::Unchanged,
+            Some(_) => Status::Modified,
+        };
+        open_names.insert(name);
+        result.push(CardStatus { status, card });
     }
 
     for card in timelinecards {
-        let face = card.face.name();
-        let sha256 = String::from(card.content_sha256);
-        timelinecards_set.insert((face, sha256));
+        if !open_names.contains(&card.face.name()) {
+            result.push(CardStatus { status: Status::Deleted, card });
+        }
     }
 
-    let modified_or_new = opencards_set.difference(&timelinecards_set);
-    let mut modified = Vec::new();
-
-    for card in modified_or_new {
-        modified.push(Meta {
-            face: Face::from_name(&card.0).unwrap(),
-            create_time: Timestamp { },
-            modify_time: Timestamp { },
-            content_sha256: card.1.clone(),
-            commit_user: String::new(),
-            commit_email: String::new(),
-        });
+    result.sort_by(|a, b| a.card.face.cmp(&b.card.face));
+    return result;
+}
+
+/// Every card that exists in the card folder, in the timeline or in both,
+/// in the card order.
+pub fn existing_cards(folder: &Path, conn: &Connection) -> Vec<Face> {
+    let mut cards: Vec<Face> = CardFolder::open(folder, conn).cards();
+    for card in all_timeline_cards(conn) {
+        cards.push(card.face);
     }
+    cards.sort();
+    cards.dedup();
+    return cards;
+}
 
-    return modified;
+/// A stored content of a card.
+pub enum Version {
+    /// The content saved last.
+    Latest,
+    /// The revision with the given running number.
+    Revision(usize),
+    /// The blob with the given hash or a unique prefix of it.
+    Hash(String),
 }
 
+impl Version {
+    pub fn describe(&self) -> String {
+        match self {
+            Version::Latest => String::from("latest"),
+            Version::Revision(number) => format!("revision {}", number),
+            Version::Hash(prefix) => format!("blob {}", prefix),
+        }
+    }
+}
+
+/// Hash of the stored content of a card.
+pub fn stored_hash(conn: &Connection, card: &Face, version: &Version) -> Result<hash::Hash, String> {
+    match version {
+        Version::Latest => {
+            carddb::card_hash(conn, card)
+                .ok_or_else(|| format!("The card {} is not in the timeline", card.name()))
+        },
+        Version::Revision(number) => {
+            let rev = revision::find_revision(conn, card, *number)
+                .map_err(|msg| format!("{} revision {}: {}", card.name(), number, msg))?;
+            hash::Hash::from_text(&rev.content_sha256)
+        },
+        Version::Hash(prefix) => blob::resolve_prefix(conn, prefix),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::control::commit::CommitModifiedCards;
+    use crate::model::schema::TestTimeline;
+
+    #[test]
+    fn test_status_after_changing_the_hash_algorithm() {
+        let mut timeline = TestTimeline::new("status-algorithm");
+        let cards = timeline.cards();
+        fs::write(cards.join("1"), "one\n").unwrap();
+        fs::write(cards.join("2"), "two\n").unwrap();
+        CommitModifiedCards::new(&mut timeline.conn, &cards, "first", "test").call_once().unwrap();
+
+        carddb::set_hash_algorithm(&timeline.conn, hash::Algorithm::Blake3).unwrap();
+        let statuses = status(&cards, &timeline.conn);
+        assert!(statuses.iter().all(|card| card.status == Status::Unchanged));
+
+        fs::write(cards.join("2"), "two and more\n").unwrap();
+        let modified = modified_open_cards(&cards, &timeline.conn);
+        assert_eq!(modified.iter().map(|card| card.name()).collect::<Vec<String>>(), vec!["2"]);
+    }
+}
@@ -0,0 +1,298 @@
+use rusqlite::{Connection, params};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use crate::control::content;
+use crate::hash;
+use crate::model::blob;
+use crate::model::cardfolder::CardFolder;
+use crate::model::tag::TagFeature;
+
+/// An inconsistency in the timeline or in the card folder.
+pub enum Problem {
+    /// The stored content cannot be read at all.
+    UnreadableBlob { rowid: i64, content_sha256: String, reason: String },
+    /// The stored content does not have the hash it is stored with.
+    HashMismatch { rowid: i64, content_sha256: String, actual: String },
+    /// The same content is stored more than once.
+    DuplicateContent { rowid: i64, content_sha256: String },
+    /// A card or a revision refers to content that is not stored.
+    MissingBlob { table: &'static str, card_name: String, content_sha256: String },
+    /// A tag is set to a card number that is neither in the timeline nor in the card folder.
+    OrphanTag { tag_name: String, major_card_number: usize },
+    /// A file in the card folder has a name that is not a card name.
+    InvalidCardFile { file_name: String },
+    /// A file in the objects directory that no content row points to.
+    StrayObject { path: PathBuf },
+}
+
+/// A safe way to fix a problem.
+pub enum Fix {
+    /// Delete a content row. Used only when a valid copy of the content remains.
+    DeleteRow(i64),
+    /// Store the file of an open card which has the missing content.
+    StoreFile(PathBuf),
+}
+
+pub struct Finding {
+    pub problem: Problem,
+    pub fix: Option<Fix>,
+}
+
+impl Problem {
+    pub fn describe(&self) -> String {
+        match self {
+            Problem::UnreadableBlob { rowid, content_sha256, reason } =>
+                format!("unreadable blob {} (row {}): {}", content_sha256, rowid, reason),
+            Problem::HashMismatch { rowid, content_sha256, actual } =>
+                format!("hash mismatch: blob {} (row {}) has content {}", content_sha256, rowid, actual),
+            Problem::DuplicateContent { rowid, content_sha256 } =>
+                format!("duplicate blob {} (row {})", content_sha256, rowid),
+            Problem::MissingBlob { table, card_name, content_sha256 } =>
+                format!("missing blob: {} {} refers to {}", table, card_name, content_sha256),
+            Problem::OrphanTag { tag_name, major_card_number } =>
+                format!("orphan tag: {} is set to card {} which does not exist", tag_name, major_card_number),
+            Problem::InvalidCardFile { file_name } =>
+                format!("invalid card file: {} is not a card name", file_name),
+            Problem::StrayObject { path } =>
+                format!("stray object: {} is not stored in the timeline", path.display()),
+        }
+    }
+}
+
+/// Verify every blob and the references between the tables and the card folder.
+pub fn check(conn: &Connection, folder: &Path) -> Result<Vec<Finding>, String> {
+    let mut findings = Vec::new();
+    let opencards = CardFolder::open(folder, conn);
+
+    // Re-hash every stored blob. Remember which hashes have a valid copy.
+    let mut valid_rows: HashMap<String, Vec<i64>> = HashMap::new();
+    let mut invalid_rows: Vec<(Problem, String)> = Vec::new();
+
+    for row in blob::all_rows(conn)? {
+        match blob::rehash_row(conn, row.rowid) {
+            Ok(actual) => {
+                let actual = actual.to_string();
+                if actual == row.content_sha256 {
+                    valid_rows.entry(row.content_sha256).or_default().push(row.rowid);
+                } else {
+                    let sha256 = row.content_sha256.clone();
+                    invalid_rows.push((Problem::HashMismatch { rowid: row.rowid, content_sha256: row.content_sha256, actual }, sha256));
+                }
+            },
+            Err(reason) => {
+                let sha256 = row.content_sha256.clone();
+                invalid_rows.push((Problem::UnreadableBlob { rowid: row.rowid, content_sha256: row.content_sha256, reason }, sha256));
+            }
+        }
+    }
+
+    for (problem, content_sha256) in invalid_rows {
+        let fix = match &problem {
+            Problem::HashMismatch { rowid, .. } | Problem::UnreadableBlob { rowid, .. }
+                if valid_rows.contains_key(&content_sha256) => Some(Fix::DeleteRow(*rowid)),
+            _ => None,
+        };
+        findings.push(Finding { problem, fix });
+    }
+
+    let mut duplicates: Vec<(&String, &Vec<i64>)> = valid_rows.iter().filter(|(_, rows)| rows.len() > 1).collect();
+    duplicates.sort();
+    for (content_sha256, rowids) in duplicates {
+        for rowid in &rowids[1..] {
+            findings.push(Finding {
+                problem: Problem::DuplicateContent { rowid: *rowid, content_sha256: content_sha256.clone() },
+                fix: Some(Fix::DeleteRow(*rowid)),
+            });
+        }
+    }
+
+    // References from cards and revisions to content that has no valid copy.
+    for (table, column) in blob::REFERENCES.iter() {
+        if !blob::table_exists(conn, table) {
+            continue;
+        }
+        for (card_name, content_sha256) in references(conn, table, column)? {
+            if valid_rows.contains_key(&content_sha256) {
+                continue;
+            }
+            let fix = open_card_with_content(&opencards, &card_name, &content_sha256).map(Fix::StoreFile);
+            findings.push(Finding {
+                problem: Problem::MissingBlob { table, card_name, content_sha256 },
+                fix,
+            });
+        }
+    }
+
+    // Tags of cards that do not exist anywhere.
+    let mut major_numbers: HashSet<usize> = opencards.cards().iter().map(|card| card.major_number()).collect();
+    for card in content::all_timeline_cards(conn) {
+        major_numbers.insert(card.major_number());
+    }
+    let tags = TagFeature::new(conn).all_tagged_cards()?;
+    for (tag_name, major_card_number) in tags {
+        if !major_numbers.contains(&major_card_number) {
+            findings.push(Finding { problem: Problem::OrphanTag { tag_name, major_card_number }, fix: None });
+        }
+    }
+
+    // Files in the objects directory are verified above through their
+    // pointer rows. Anything else there is left over from an interrupted save.
+    if let Some(objects) = blob::objects_dir(conn) {
+        let pointed: HashSet<PathBuf> = blob::all_rows(conn)?.iter()
+            .filter(|row| row.object)
+            .map(|row| blob::object_path(&objects, &row.content_sha256, row.codec))
+            .collect();
+        for path in object_files(&objects) {
+            if !pointed.contains(&path) {
+                findings.push(Finding { problem: Problem::StrayObject { path }, fix: None });
+            }
+        }
+    }
+
+    let mut other_files = opencards.other_files();
+    other_files.sort();
+    for file_name in other_files {
+        findings.push(Finding { problem: Problem::InvalidCardFile { file_name }, fix: None });
+    }
+
+    return Ok(findings);
+}
+
+/// Every file in the objects directory and its subdirectories, sorted. The
+/// SHA-256 objects are in objects/ab/ and the others in objects/blake3/ab/.
+fn object_files(objects: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![objects.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.is_file() {
+                    files.push(path);
+                }
+            }
+        }
+    }
+    files.sort();
+    return files;
+}
+
+fn references(conn: &Connection, table: &str, column: &str) -> Result<Vec<(String, String)>, String> {
+    let sql = format!("select card_name, {} from {} where {} is not null order by 1;", column, table, column);
+    let mut stmt = conn.prepare(&sql).map_err(|_| String::from("Fail to prepare a query"))?;
+    let rows = stmt.query_map(params![], |row| {
+        let card_name: String = row.get(0)?;
+        let content_sha256: String = row.get(1)?;
+        Ok((card_name, content_sha256))
+    }).map_err(|_| format!("Fail to read the {} table", table))?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row.map_err(|_| format!("Fail to read the {} table", table))?);
+    }
+    return Ok(result);
+}
+
+/// The file of the open card if it has the given content.
+fn open_card_with_content(opencards: &CardFolder, card_name: &str, content_sha256: &str) -> Option<PathBuf> {
+    let file = opencards.config.card(card_name)?.location_in(&opencards.folder, &opencards.config);
+    let (algorithm, _) = hash::split_algorithm(content_sha256).ok()?;
+    let hash = hash::Hash::file(&file, algorithm).ok()?;
+    if hash.to_string() == content_sha256 {
+        return Some(file);
+    }
+    return None;
+}
+
+/// Apply the safe fixes in one transaction. Return how many were applied.
+pub fn repair(conn: &mut Connection, findings: &[Finding]) -> Result<usize, String> {
+    let objects = blob::objects_dir(conn);
+    let savepoint = conn.savepoint().map_err(|_| String::from("Fail to start a transaction"))?;
+    let mut count = 0;
+    let mut deleted_objects: Vec<PathBuf> = Vec::new();
+
+    for finding in findings {
+        match &finding.fix {
+            Some(Fix::DeleteRow(rowid)) => {
+                let row = blob::row_by_id(&savepoint, *rowid)?;
+                if let (true, Some(objects)) = (row.object, &objects) {
+                    deleted_objects.push(blob::object_path(objects, &row.content_sha256, row.codec));
+                }
+                blob::delete_row(&savepoint, *rowid)?;
+            },
+            Some(Fix::StoreFile(file)) => { blob::save(&savepoint, file)?; },
+            None => continue,
+        }
+        count += 1;
+    }
+
+    savepoint.commit().map_err(|_| String::from("Fail to commit the transaction"))?;
+
+    // The object files cannot be rolled back. Remove them only after their
+    // rows are gone and only if no remaining row, like a duplicate of the
+    // same content, points to them.
+    if let Some(objects) = objects {
+        let pointed: HashSet<PathBuf> = blob::all_rows(conn)?.iter()
+            .filter(|row| row.object)
+            .map(|row| blob::object_path(&objects, &row.content_sha256, row.codec))
+            .collect();
+        for object in deleted_objects {
+            if !pointed.contains(&object) {
+                blob::remove_object_file(&object)?;
+            }
+        }
+    }
+    return Ok(count);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::carddb;
+    use crate::model::schema::TestTimeline;
+    use std::fs;
+
+    #[test]
+    fn test_objects_of_other_algorithms() {
+        let mut timeline = TestTimeline::new("fsck-objects");
+        let (dir, cards) = (timeline.dir.clone(), timeline.cards());
+        carddb::set_sidecar_threshold(&timeline.conn, Some(1)).unwrap();
+        carddb::set_hash_algorithm(&timeline.conn, hash::Algorithm::Blake3).unwrap();
+        fs::write(dir.join("large"), b"abcdefgh").unwrap();
+        let large = blob::save(&timeline.conn, &dir.join("large")).unwrap();
+        let row = &blob::all_rows(&timeline.conn).unwrap()[0];
+        let object = blob::object_path(&dir.join("objects"), &large.to_string(), row.codec);
+        assert!(object.starts_with(dir.join("objects").join("blake3")));
+
+        let stray = object.with_file_name("stray");
+        fs::write(&stray, b"left over").unwrap();
+        let findings = check(&timeline.conn, &cards).unwrap();
+        let strays: Vec<&PathBuf> = findings.iter().filter_map(|finding| match &finding.problem {
+            Problem::StrayObject { path } => Some(path),
+            _ => None,
+        }).collect();
+        assert_eq!(strays, vec![&stray]);
+        fs::remove_file(&stray).unwrap();
+
+        // Deleting a duplicate row keeps the object file of the other row.
+        timeline.conn.execute("insert into content select * from content;", params![]).unwrap();
+        let findings = check(&timeline.conn, &cards).unwrap();
+        assert_eq!(repair(&mut timeline.conn, &findings), Ok(1));
+        assert!(object.is_file());
+        assert_eq!(blob::load(&timeline.conn, large.clone()), Some(b"abcdefgh".to_vec()));
+        assert!(check(&timeline.conn, &cards).unwrap().is_empty());
+
+        // Deleting a broken row removes its object file when a copy in the
+        // timeline file remains.
+        timeline.conn.execute(
+            "insert into content(content_sha256, blob, codec) values (?1, ?2, 'none');",
+            params![large.to_string(), b"abcdefgh".to_vec()]
+        ).unwrap();
+        fs::write(&object, b"broken").unwrap();
+        let findings = check(&timeline.conn, &cards).unwrap();
+        assert_eq!(repair(&mut timeline.conn, &findings), Ok(1));
+        assert!(!object.exists());
+        assert!(check(&timeline.conn, &cards).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+use rusqlite::Connection;
+use crate::card::Timestamp;
+use crate::model::blob;
+use crate::model::blob::BlobInfo;
+
+/// A command to delete the blobs no card, revision or other table refers to.
+pub struct CollectGarbage<'a> {
+    connection: &'a mut Connection,
+    blobs: Vec<BlobInfo>,
+}
+
+impl<'a> CollectGarbage<'a> {
+    /// Find the garbage. With a time given, keep blobs stored after it.
+    pub fn new(connection: &'a mut Connection, stored_before: Option<&Timestamp>) -> Result<CollectGarbage<'a>, &'static str> {
+        let blobs = blob::unreferenced(connection, stored_before)?;
+        Ok(CollectGarbage { connection, blobs })
+    }
+
+    /// Blobs that will be deleted.
+    pub fn blobs(&self) -> &Vec<BlobInfo> {
+        &self.blobs
+    }
+
+    pub fn reclaimed_bytes(&self) -> usize {
+        self.blobs.iter().map(|info| info.size).sum()
+    }
+
+    /// Delete the blobs in one transaction and optionally vacuum the timeline
+    /// file afterwards to give the space back to the filesystem.
+    pub fn call_once(self, vacuum: bool) -> Result<(), &'static str> {
+        let savepoint = self.connection.savepoint();
+        if savepoint.is_err() {
+            return Err("Fail to start a transaction");
+        }
+        let savepoint = savepoint.unwrap();
+
+        for info in &self.blobs {
+            blob::delete(&savepoint, &info.content_sha256)?;
+        }
+
+        if savepoint.commit().is_err() {
+            return Err("Fail to commit the transaction");
+        }
+
+        // The files in the objects directory cannot be rolled back. Remove
+        // them only after the rows are gone.
+        for info in &self.blobs {
+            blob::remove_object(self.connection, &info.content_sha256)?;
+        }
+
+        if vacuum && self.connection.execute_batch("vacuum;").is_err() {
+            return Err("Fail to vacuum the timeline");
+        }
+        Ok(())
+    }
+}
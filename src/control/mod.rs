@@ -1,3 +1,12 @@
+pub mod attachment;
+pub mod commit;
 pub mod content;
+pub mod fsck;
+pub mod gc;
+pub mod recompress;
+pub mod rehash;
+pub mod relayout;
+pub mod rename;
+pub mod repattern;
+pub mod restore;
 pub mod tag;
-
@@ -0,0 +1,68 @@
+use rusqlite::Connection;
+use crate::hash;
+use crate::model::blob;
+use crate::model::codec::Codec;
+
+/// A command to store every blob again with another codec. Each blob is
+/// converted in its own transaction so an interrupted run loses no work.
+pub struct RecompressBlobs<'a> {
+    connection: &'a mut Connection,
+    codec: Codec,
+}
+
+pub struct Recompressed {
+    pub blobs: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+impl<'a> RecompressBlobs<'a> {
+    pub fn new(connection: &'a mut Connection, codec: Codec) -> RecompressBlobs<'a> {
+        RecompressBlobs { connection, codec }
+    }
+
+    pub fn call_once(self) -> Result<Recompressed, String> {
+        let rows = blob::all_rows(self.connection)?;
+        let mut result = Recompressed { blobs: 0, bytes_before: 0, bytes_after: 0 };
+
+        for (i, row) in rows.iter().enumerate() {
+            // Keep the hash the content is stored with.
+            let (algorithm, _) = hash::split_algorithm(&row.content_sha256)?;
+            let connection: &Connection = self.connection;
+            let measure = blob::measure(&|| blob::open_row(connection, row.rowid), self.codec, algorithm)
+                .map_err(|msg| format!("Fail to read blob {}. {}", row.content_sha256, msg))?;
+            if blob::choose_codec(&measure, self.codec) == row.codec {
+                continue;
+            }
+
+            // Note(wistrandj): Open the savepoint only for the rows that
+            // change. A savepoint dropped without commit is not released.
+            let savepoint = self.connection.savepoint()
+                .map_err(|_| String::from("Fail to start a transaction"))?;
+            let source = || blob::open_row(&savepoint, row.rowid);
+
+            let rowid = blob::write_new(&savepoint, &source, &measure, self.codec, row.object)
+                .map_err(|msg| format!("Fail to store blob {}. {}", row.content_sha256, msg))?;
+            blob::replace_row(&savepoint, row.rowid, rowid)?;
+            let stored_size = blob::row_by_id(&savepoint, rowid)?.stored_size;
+            savepoint.commit().map_err(|_| String::from("Fail to commit the transaction"))?;
+
+            // The new object file has another name. The old one cannot be
+            // removed before the new row is committed, nor while a duplicate
+            // row still to be converted points to it.
+            let shared = rows[i + 1..].iter()
+                .any(|other| other.object && other.codec == row.codec && other.content_sha256 == row.content_sha256);
+            if row.object && !shared {
+                if let Some(objects) = blob::objects_dir(self.connection) {
+                    blob::remove_object_file(&blob::object_path(&objects, &row.content_sha256, row.codec))?;
+                }
+            }
+
+            result.blobs += 1;
+            result.bytes_before += row.stored_size;
+            result.bytes_after += stored_size;
+        }
+
+        return Ok(result);
+    }
+}
@@ -0,0 +1,135 @@
+use rusqlite::{Connection, params};
+use std::fs;
+use std::io;
+use crate::hash;
+use crate::model::blob;
+use crate::model::carddb;
+
+/// A command to identify every blob by a hash of another algorithm and to
+/// make it the default of the timeline. The old hashes are kept as aliases
+/// so that they can still be used to find the content. Each blob is
+/// rehashed in its own transaction so that an interrupted run can be
+/// continued by running it again.
+pub struct RehashContent<'a> {
+    connection: &'a mut Connection,
+    algorithm: hash::Algorithm,
+}
+
+/// Calculate the hash of the old and the new algorithm in one read.
+struct TwoHashers {
+    old: hash::Hasher,
+    new: hash::Hasher,
+}
+
+impl io::Write for TwoHashers {
+    fn write(&mut self, data: &[u8]) -> Result<usize, io::Error> {
+        self.old.update(data);
+        self.new.update(data);
+        return Ok(data.len());
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        return Ok(());
+    }
+}
+
+impl<'a> RehashContent<'a> {
+    pub fn new(connection: &'a mut Connection, algorithm: hash::Algorithm) -> RehashContent<'a> {
+        RehashContent { connection, algorithm }
+    }
+
+    /// Return how many blobs were rehashed.
+    pub fn call_once(self) -> Result<usize, String> {
+        let rows = blob::all_rows(self.connection)?;
+        let objects = blob::objects_dir(self.connection);
+        let mut count = 0;
+
+        for (i, row) in rows.iter().enumerate() {
+            let (algorithm, _) = hash::split_algorithm(&row.content_sha256)?;
+            if algorithm == self.algorithm {
+                continue;
+            }
+
+            let mut hashers = TwoHashers { old: hash::Hasher::new(algorithm), new: hash::Hasher::new(self.algorithm) };
+            let mut input = blob::open_row(self.connection, row.rowid)?;
+            io::copy(&mut input, &mut hashers)
+                .map_err(|msg| format!("Fail to read blob {}. Reason: {}", row.content_sha256, msg))?;
+            drop(input);
+            if hashers.old.finish().to_string() != row.content_sha256 {
+                return Err(format!("The blob {} does not match its hash. Run zk fsck first", row.content_sha256));
+            }
+            let new_id = hashers.new.finish().to_string();
+
+            // Link the object file under its new name before the row points to it.
+            if let (true, Some(objects)) = (row.object, &objects) {
+                let old_file = blob::object_path(objects, &row.content_sha256, row.codec);
+                let new_file = blob::object_path(objects, &new_id, row.codec);
+                link_or_copy(&old_file, &new_file)?;
+            }
+
+            let savepoint = self.connection.savepoint()
+                .map_err(|_| String::from("Fail to start a transaction"))?;
+            rename_content(&savepoint, row.rowid, &row.content_sha256, &new_id)?;
+            savepoint.commit().map_err(|_| String::from("Fail to commit the transaction"))?;
+
+            // A duplicate row still to be rehashed needs the old object file.
+            let shared = rows[i + 1..].iter()
+                .any(|other| other.object && other.codec == row.codec && other.content_sha256 == row.content_sha256);
+            if let (true, false, Some(objects)) = (row.object, shared, &objects) {
+                blob::remove_object_file(&blob::object_path(objects, &row.content_sha256, row.codec))?;
+            }
+            count += 1;
+        }
+
+        carddb::set_hash_algorithm(self.connection, self.algorithm)?;
+        return Ok(count);
+    }
+}
+
+/// Identify the content of the row by the new hash everywhere and record
+/// the old hash as an alias of it.
+fn rename_content(conn: &Connection, rowid: i64, old_id: &str, new_id: &str) -> Result<(), String> {
+    let fail = |msg: rusqlite::Error| format!("Fail to rehash blob {}. Reason: {}", old_id, msg);
+
+    let stored: u32 = conn.query_row(
+        "select count(*) from content where content_sha256 = ?1;",
+        params![new_id],
+        |row| row.get(0)
+    ).map_err(fail)?;
+    if stored > 0 {
+        blob::delete_row(conn, rowid)?;
+    } else {
+        conn.execute("update content set content_sha256 = ?1 where rowid = ?2;", params![new_id, rowid])
+            .map_err(fail)?;
+    }
+
+    for (table, column) in blob::REFERENCES.iter() {
+        if !blob::table_exists(conn, table) {
+            continue;
+        }
+        let sql = format!("update {} set {} = ?1 where {} = ?2;", table, column, column);
+        conn.execute(&sql, params![new_id, old_id]).map_err(fail)?;
+    }
+
+    conn.execute("update hash_alias set new_id = ?1 where new_id = ?2;", params![new_id, old_id])
+        .map_err(fail)?;
+    conn.execute("insert or replace into hash_alias(old_id, new_id) values (?1, ?2);", params![old_id, new_id])
+        .map_err(fail)?;
+    // Rehashing back to an earlier algorithm makes some aliases point to themselves.
+    conn.execute("delete from hash_alias where old_id = new_id;", params![]).map_err(fail)?;
+    return Ok(());
+}
+
+fn link_or_copy(from: &std::path::Path, to: &std::path::Path) -> Result<(), String> {
+    if to.is_file() {
+        return Ok(());
+    }
+    if let Some(dir) = to.parent() {
+        fs::create_dir_all(dir).map_err(|_| String::from("Fail to create the objects directory"))?;
+    }
+    if fs::hard_link(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|msg| format!("Fail to copy {}. Reason: {}", from.display(), msg))?;
+    return Ok(());
+}
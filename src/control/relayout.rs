@@ -0,0 +1,89 @@
+use rusqlite::Connection;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::card::layout::FolderLayout;
+use crate::control::content;
+use crate::model::carddb;
+use crate::model::cardfolder::CardFolder;
+
+/// A command to move the card files and the attachment folders of the cards
+/// to another folder layout, like from the flat folder to ranges:1000.
+pub struct Relayout<'a> {
+    connection: &'a Connection,
+    folder: PathBuf,
+    old_layout: FolderLayout,
+    layout: FolderLayout,
+    moves: Vec<(PathBuf, PathBuf)>,
+}
+
+impl<'a> Relayout<'a> {
+    /// Plan the moves. Refuse if a file is in the way of a moved one.
+    pub fn new(connection: &'a Connection, folder: &Path, layout: FolderLayout) -> Result<Relayout<'a>, String> {
+        let mut moves = Vec::new();
+        let opencards = CardFolder::open(folder, connection);
+        for (card, file) in opencards.card_files() {
+            let new_file = layout.folder_of(folder, &card).join(file.file_name().unwrap());
+            moves.push((file, new_file));
+        }
+        // Cards that are only in the timeline may have attachments too.
+        for card in content::existing_cards(folder, connection) {
+            let attachments = card.attachments_in(folder, &opencards.config);
+            if attachments.is_dir() {
+                let new_attachments = layout.folder_of(folder, &card).join(attachments.file_name().unwrap());
+                moves.push((attachments, new_attachments));
+            }
+        }
+        moves.retain(|(from, to)| from != to);
+        moves.sort();
+
+        for (_, to) in &moves {
+            if to.exists() {
+                return Err(format!("Cannot move the cards: {} exists", to.to_string_lossy()));
+            }
+        }
+        Ok(Relayout { connection, folder: folder.to_path_buf(), old_layout: opencards.config.layout, layout, moves })
+    }
+
+    /// The files and folders to move and where.
+    pub fn moves(&self) -> &Vec<(PathBuf, PathBuf)> {
+        &self.moves
+    }
+
+    /// Move the files and set the layout. The moves are undone if any of
+    /// them fails. Return the number of moves.
+    pub fn call_once(self) -> Result<usize, String> {
+        let mut done: Vec<&(PathBuf, PathBuf)> = Vec::new();
+        let mut success = Ok(());
+        for change in &self.moves {
+            let (from, to) = change;
+            success = fs::create_dir_all(to.parent().unwrap())
+                .and_then(|_| fs::rename(from, to))
+                .map_err(|msg| format!("Fail to move {}. Reason: {}", from.to_string_lossy(), msg));
+            if success.is_err() {
+                break;
+            }
+            done.push(change);
+        }
+        if success.is_ok() {
+            success = carddb::set_folder_layout(self.connection, self.layout).map_err(String::from);
+        }
+        if let Err(msg) = success {
+            for (from, to) in done.into_iter().rev() {
+                if fs::rename(to, from).is_err() {
+                    eprintln!("Fail to move back {}", to.to_string_lossy());
+                }
+            }
+            return Err(msg);
+        }
+
+        // The subfolders of the old layout that were emptied.
+        for (from, _) in &self.moves {
+            let parent = from.parent().unwrap();
+            let name = parent.file_name().unwrap_or_default().to_string_lossy();
+            if parent != self.folder && self.old_layout.is_subfolder(&name) {
+                let _ = fs::remove_dir(parent);
+            }
+        }
+        return Ok(self.moves.len());
+    }
+}
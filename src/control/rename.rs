@@ -0,0 +1,319 @@
+use rusqlite::{Connection, params};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::card::Face;
+use crate::card::config::CardConfig;
+use crate::control::content;
+use crate::model::{blob, carddb};
+use crate::model::cardfolder::CardFolder;
+use crate::model::tag::TagFeature;
+
+/// A command to move a card and every card below it to another name, like
+/// 123a to 45c and 123a1 to 45c1. The cards are renamed in the card folder
+/// and in the timeline, and the links to them in the text of the open cards
+/// are rewritten.
+pub struct MoveCards<'a> {
+    connection: &'a mut Connection,
+    folder: PathBuf,
+    config: CardConfig,
+    from: Face,
+    to: Face,
+    renames: Vec<(Face, Face)>,
+    rewrites: Vec<Rewrite>,
+}
+
+/// New text of an open card that links to moved cards.
+pub struct Rewrite {
+    pub card: Face,
+    pub references: usize,
+    /// The changed lines: the line number from 1, the old and the new line.
+    pub lines: Vec<(usize, String, String)>,
+    text: String,
+}
+
+/// A change in the card folder that can be undone.
+enum FileChange {
+    Renamed { from: PathBuf, to: PathBuf },
+    Written { file: PathBuf, old_text: Vec<u8> },
+}
+
+/// Replace every link to a card that is a key of the map. A link is the
+/// card name in double brackets like [[123a]]. A bare 123a or a "step 1" in
+/// the text is not a link. Return the number of replacements.
+pub fn rewrite_references(text: &str, map: &HashMap<String, String>) -> (String, usize) {
+    let mut result = String::with_capacity(text.len());
+    let mut count = 0;
+    let mut rest = text;
+
+    while let Some(start) = rest.find("[[") {
+        let (before, link) = rest.split_at(start + 2);
+        result.push_str(before);
+        rest = link;
+        let name = rest.find("]]").map(|end| &rest[..end]);
+        if let Some(new) = name.and_then(|name| map.get(name)) {
+            result.push_str(new);
+            rest = &rest[name.unwrap().len()..];
+            count += 1;
+        }
+    }
+    result.push_str(rest);
+    return (result, count);
+}
+
+/// The lines that differ between two texts of the same number of lines.
+fn changed_lines(old: &str, new: &str) -> Vec<(usize, String, String)> {
+    return old.lines().zip(new.lines()).enumerate()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(i, (old, new))| (i + 1, String::from(old), String::from(new)))
+        .collect();
+}
+
+impl<'a> MoveCards<'a> {
+    /// Plan the move. Refuse if a new name is taken by a card that does not move.
+    pub fn new(connection: &'a mut Connection, folder: &Path, from: &Face, to: &Face) -> Result<MoveCards<'a>, String> {
+        if from == to {
+            return Err(String::from("The card is already there"));
+        }
+        if from.is_ancestor_of(to) {
+            return Err(format!("Cannot move {} under itself", from.name()));
+        }
+
+        // Every name the card folder or the timeline knows, including the
+        // history of deleted cards and the reserved names.
+        let mut known: HashSet<String> = content::existing_cards(folder, connection).iter()
+            .map(|card| card.name())
+            .collect();
+        known.extend(carddb::timeline_card_names(connection)?);
+        if !known.contains(&from.name()) {
+            return Err(format!("The card {} does not exist", from.name()));
+        }
+
+        // Tags belong to the major cards. Below another card the tags of the
+        // moved card would have no card to belong to.
+        if from.is_major() && !to.is_major() {
+            let tags = TagFeature::new(connection).find_tags_of_cards(&vec![from.major_number()])?;
+            if !tags.is_empty() {
+                return Err(format!("The card {} has the tags {}. Delete them before moving it below another card",
+                    from.name(), tags.join(", ")));
+            }
+        }
+
+        let opencards = CardFolder::open(folder, connection);
+        let mut renames: Vec<(Face, Face)> = known.iter()
+            .filter_map(|name| opencards.config.card(name))
+            .filter_map(|card| card.rebase(from, to).map(|new| (card, new)))
+            .collect();
+        renames.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let moving: HashSet<String> = renames.iter().map(|(old, _)| old.name()).collect();
+        for (old, new) in &renames {
+            if known.contains(&new.name()) && !moving.contains(&new.name()) {
+                return Err(format!("Cannot move {} to {}: the card {} exists", old.name(), new.name(), new.name()));
+            }
+        }
+
+        let map: HashMap<String, String> = renames.iter().map(|(old, new)| (old.name(), new.name())).collect();
+        let mut rewrites = Vec::new();
+        for (card, file) in opencards.card_files() {
+            let text = fs::read(file)
+                .map_err(|_| format!("Fail to read the card {}", card.name()))?;
+            // Leave binary files and files in other encodings as they are.
+            if let Ok(old_text) = String::from_utf8(text) {
+                let (text, references) = rewrite_references(&old_text, &map);
+                if references > 0 {
+                    let lines = changed_lines(&old_text, &text);
+                    rewrites.push(Rewrite { card, references, lines, text });
+                }
+            }
+        }
+        rewrites.sort_by(|a, b| a.card.cmp(&b.card));
+
+        let config = opencards.config;
+        Ok(MoveCards { connection, folder: folder.to_path_buf(), config, from: from.clone(), to: to.clone(), renames, rewrites })
+    }
+
+    /// Old and new names of every moved card.
+    pub fn renames(&self) -> &Vec<(Face, Face)> {
+        &self.renames
+    }
+
+    /// Open cards whose text links to moved cards.
+    pub fn rewrites(&self) -> &Vec<Rewrite> {
+        &self.rewrites
+    }
+
+    /// Rename in the timeline in one transaction. The changes in the card
+    /// folder are undone if the transaction fails.
+    pub fn call_once(self) -> Result<(), String> {
+        let savepoint = self.connection.savepoint()
+            .map_err(|_| String::from("Fail to start a transaction"))?;
+        rename_in_timeline(&savepoint, &self.renames, &self.from, &self.to)?;
+
+        let mut changes = Vec::new();
+        let success = change_folder(&self.folder, &self.config, &self.renames, &self.rewrites, &mut changes)
+            .and_then(|_| savepoint.commit().map_err(|_| String::from("Fail to commit the transaction")));
+        if let Err(msg) = success {
+            undo(changes);
+            return Err(msg);
+        }
+        return Ok(());
+    }
+}
+
+fn rename_in_timeline(conn: &Connection, renames: &[(Face, Face)], from: &Face, to: &Face) -> Result<(), String> {
+    let fail = |msg: rusqlite::Error| format!("Fail to rename the cards in the timeline. Reason: {}", msg);
+
+    // Through temporary names because a new name may be the old name of
+    // another moved card and the card names are unique.
+    for table in carddb::CARD_NAME_TABLES.iter() {
+        if !blob::table_exists(conn, table) {
+            continue;
+        }
+        let sql = format!("update {} set card_name = ?2 where card_name = ?1;", table);
+        for (old, new) in renames {
+            conn.execute(&sql, params![old.name(), format!("~{}", new.name())]).map_err(fail)?;
+        }
+        for (_, new) in renames {
+            conn.execute(&sql, params![format!("~{}", new.name()), new.name()]).map_err(fail)?;
+        }
+    }
+
+    // Tags belong to the major cards. They move only from a major card to
+    // another major card.
+    if from.is_major() && to.is_major() && from.major_number() != to.major_number() {
+        let (old, new) = (from.major_number() as i64, to.major_number() as i64);
+        conn.execute("update or ignore tag set major_card_number = ?2 where major_card_number = ?1;", params![old, new])
+            .map_err(fail)?;
+        conn.execute("delete from tag where major_card_number = ?1;", params![old]).map_err(fail)?;
+        conn.execute("update tag_history set major_card_number = ?2 where major_card_number = ?1;", params![old, new])
+            .map_err(fail)?;
+    }
+    return Ok(());
+}
+
+fn rename_file(from: PathBuf, to: PathBuf, changes: &mut Vec<FileChange>) -> Result<(), String> {
+    if !from.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|_| format!("Fail to create {}", parent.to_string_lossy()))?;
+    }
+    fs::rename(&from, &to)
+        .map_err(|msg| format!("Fail to rename {}. Reason: {}", from.to_string_lossy(), msg))?;
+    changes.push(FileChange::Renamed { from, to });
+    return Ok(());
+}
+
+fn change_folder(folder: &Path, config: &CardConfig, renames: &[(Face, Face)], rewrites: &[Rewrite], changes: &mut Vec<FileChange>) -> Result<(), String> {
+    // The new file keeps the slug of the old one: 123a-notes.md -> 45c-notes.md.
+    let pattern = &config.pattern;
+    let mut moves: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut moved: HashMap<String, PathBuf> = HashMap::new();
+    for (old, new) in renames {
+        let file = old.location_in(folder, config);
+        let slug = file.file_name()
+            .and_then(|name| pattern.parse(&config.scheme, &name.to_string_lossy()))
+            .map(|(_, slug)| slug)
+            .unwrap_or_default();
+        let new_file = config.layout.folder_of(folder, new).join(pattern.file_name(new, &slug));
+        moved.insert(old.name(), new_file.clone());
+        moves.push((file, new_file));
+        moves.push((old.attachments_in(folder, config), new.attachments_in(folder, config)));
+    }
+
+    // Through temporary names like in the timeline: 45c is first ~45c.
+    let temporary = |path: &PathBuf| folder.join(format!("~{}", path.file_name().unwrap().to_string_lossy()));
+    for (from, to) in &moves {
+        rename_file(from.clone(), temporary(to), changes)?;
+    }
+    for (_, to) in &moves {
+        rename_file(temporary(to), to.clone(), changes)?;
+    }
+
+    for rewrite in rewrites {
+        let file = moved.get(&rewrite.card.name()).cloned().unwrap_or_else(|| rewrite.card.location_in(folder, config));
+        let old_text = fs::read(&file).map_err(|_| format!("Fail to read the card {}", rewrite.card.name()))?;
+        fs::write(&file, &rewrite.text).map_err(|_| format!("Fail to write the card {}", rewrite.card.name()))?;
+        changes.push(FileChange::Written { file, old_text });
+    }
+    return Ok(());
+}
+
+/// Undo the changes in the card folder in the reverse order.
+fn undo(changes: Vec<FileChange>) {
+    for change in changes.into_iter().rev() {
+        let success = match &change {
+            FileChange::Renamed { from, to } => fs::rename(to, from),
+            FileChange::Written { file, old_text } => fs::write(file, old_text),
+        };
+        if success.is_err() {
+            let file = match &change {
+                FileChange::Renamed { to, .. } => to,
+                FileChange::Written { file, .. } => file,
+            };
+            eprintln!("Fail to undo the change of {}", file.to_string_lossy());
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::card::naming::NamingScheme;
+    use crate::model::schema::TestTimeline;
+
+    fn tags_of(conn: &Connection, major_number: usize) -> Vec<String> {
+        return TagFeature::new(conn).find_tags_of_cards(&vec![major_number]).unwrap();
+    }
+
+    #[test]
+    fn test_move_tags() {
+        let mut timeline = TestTimeline::new("move-tags");
+        for name in ["123", "123a", "124", "45"].iter() {
+            fs::write(timeline.cards().join(name), name).unwrap();
+        }
+        TagFeature::new(&timeline.conn).set_tag_to_card("idea", 123).unwrap();
+        TagFeature::new(&timeline.conn).set_tag_to_card("book", 45).unwrap();
+        TagFeature::new(&timeline.conn).insert_tag_to_a_batch_history(1, "draft", 124).unwrap();
+        let folder = timeline.cards();
+        let card = |name: &str| NamingScheme::Alphanumeric.parse(name).unwrap();
+
+        // A major card with tags does not move below another card.
+        let refused = MoveCards::new(&mut timeline.conn, &folder, &card("123"), &card("45c"));
+        assert!(refused.err().unwrap().contains("idea"));
+
+        // A major card without tags does. The tags of 45 stay with 45.
+        MoveCards::new(&mut timeline.conn, &folder, &card("124"), &card("45c")).unwrap().call_once().unwrap();
+        assert_eq!(tags_of(&timeline.conn, 45), vec!["book"]);
+        let history: i64 = timeline.conn.query_row("select major_card_number from tag_history;", params![], |row| row.get(0)).unwrap();
+        assert_eq!(history, 124);
+        assert!(folder.join("45c").is_file());
+
+        // The tags move with a major card to another major card.
+        MoveCards::new(&mut timeline.conn, &folder, &card("123"), &card("7")).unwrap().call_once().unwrap();
+        assert_eq!(tags_of(&timeline.conn, 7), vec!["idea"]);
+        assert!(tags_of(&timeline.conn, 123).is_empty());
+        assert!(folder.join("7a").is_file());
+    }
+
+    #[test]
+    fn test_rewrite_references() {
+        let map: HashMap<String, String> = [("123a", "45c"), ("123a1", "45c1")].iter()
+            .map(|(old, new)| (old.to_string(), new.to_string()))
+            .collect();
+        let (text, count) = rewrite_references("See [[123a]], [[123a1]] and 123a.\n[[0123a]] [[123a1b]] [123a] [[123a", &map);
+        assert_eq!(text, "See [[45c]], [[45c1]] and 123a.\n[[0123a]] [[123a1b]] [123a] [[123a");
+        assert_eq!(count, 2);
+        assert_eq!(rewrite_references("", &map), (String::new(), 0));
+        assert_eq!(changed_lines("a\n[[123a]]\n", "a\n[[45c]]\n"), vec![(2, String::from("[[123a]]"), String::from("[[45c]]"))]);
+
+        let map: HashMap<String, String> = [("21/3", "7/1"), ("21/3a", "7/1a"), ("1", "7")].iter()
+            .map(|(old, new)| (old.to_string(), new.to_string()))
+            .collect();
+        let (text, count) = rewrite_references("[[21/3]], [[21/3a]]. [[21/3a,1]] [[21/30]] step 1 of [[1]]", &map);
+        assert_eq!(text, "[[7/1]], [[7/1a]]. [[21/3a,1]] [[21/30]] step 1 of [[7]]");
+        assert_eq!(count, 3);
+    }
+}
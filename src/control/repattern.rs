@@ -0,0 +1,61 @@
+use rusqlite::Connection;
+use std::fs;
+use std::path::PathBuf;
+use crate::card::pattern::FilePattern;
+use crate::model::carddb;
+use crate::model::cardfolder::CardFolder;
+
+/// A command to rename the card files to another file pattern, like from
+/// {name} to {name}-{slug}.md.
+pub struct Repattern<'a> {
+    connection: &'a Connection,
+    pattern: FilePattern,
+    renames: Vec<(PathBuf, PathBuf)>,
+}
+
+impl<'a> Repattern<'a> {
+    /// Plan the renames. Refuse if a new file name is taken.
+    pub fn new(connection: &'a Connection, opencards: &CardFolder, pattern: &FilePattern) -> Result<Repattern<'a>, String> {
+        let renames = opencards.renames_to_pattern(pattern)?;
+        Ok(Repattern { connection, pattern: pattern.clone(), renames })
+    }
+
+    /// Rename the files and set the pattern. The renames are undone if any
+    /// of them fails. Return the number of renamed files.
+    pub fn call_once(self) -> Result<usize, String> {
+        // Through temporary names because a new name may be the old name of
+        // another renamed file: 123a.md is first ~123a.md.
+        let temporary = |path: &PathBuf| path.with_file_name(format!("~{}", path.file_name().unwrap().to_string_lossy()));
+        let mut steps: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for (from, to) in &self.renames {
+            steps.push((from.clone(), temporary(to)));
+        }
+        for (_, to) in &self.renames {
+            steps.push((temporary(to), to.clone()));
+        }
+
+        let mut done: Vec<&(PathBuf, PathBuf)> = Vec::new();
+        let mut success = Ok(());
+        for step in &steps {
+            let (from, to) = step;
+            success = fs::rename(from, to)
+                .map_err(|msg| format!("Fail to rename {}. Reason: {}", from.to_string_lossy(), msg));
+            if success.is_err() {
+                break;
+            }
+            done.push(step);
+        }
+        if success.is_ok() {
+            success = carddb::set_file_pattern(self.connection, &self.pattern).map_err(String::from);
+        }
+        if let Err(msg) = success {
+            for (from, to) in done.into_iter().rev() {
+                if fs::rename(to, from).is_err() {
+                    eprintln!("Fail to rename back {}", to.to_string_lossy());
+                }
+            }
+            return Err(msg);
+        }
+        return Ok(self.renames.len());
+    }
+}
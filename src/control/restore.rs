@@ -0,0 +1,133 @@
+use rusqlite::Connection;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::card::Face;
+use crate::card::config::CardConfig;
+use crate::control::content;
+use crate::control::content::Version;
+use crate::hash;
+use crate::model::{attachment, blob, carddb, revision};
+
+/// A command to write a stored content of a card and its attachments back
+/// into the card folder.
+pub struct RestoreCard<'a> {
+    connection: &'a Connection,
+    folder: PathBuf,
+    config: CardConfig,
+    card: Face,
+    file: PathBuf,
+    hash: hash::Hash,
+}
+
+/// A command to write the latest content and the attachments of every card
+/// into an empty folder.
+pub struct Checkout<'a> {
+    connection: &'a Connection,
+    folder: PathBuf,
+    config: CardConfig,
+}
+
+impl<'a> RestoreCard<'a> {
+    /// Restore the given stored content of the card. Refuse to overwrite a
+    /// card file whose content is not in the timeline unless forced.
+    pub fn new(connection: &'a Connection, folder: &Path, card: &Face, version: &Version, force: bool) -> Result<RestoreCard<'a>, String> {
+        let hash = content::stored_hash(connection, card, version)?;
+
+        let config = carddb::card_config(connection);
+        let file = card_file(connection, folder, &config, card, &hash);
+        if file.exists() && !force {
+            let current = hash::Hash::file(&file, hash.algorithm()).map_err(|_| format!("Fail to read {}", file.to_string_lossy()))?;
+            let saved = revision::revisions_of_card(connection, card)?
+                .iter()
+                .any(|rev| rev.content_sha256 == current.to_string());
+            if !saved {
+                return Err(format!("The card {} has changes that are not saved. Use --force to overwrite them", card.name()));
+            }
+        }
+
+        Ok(RestoreCard { connection, folder: folder.to_path_buf(), config, card: card.clone(), file, hash })
+    }
+
+    pub fn call_once(self) -> Result<(), String> {
+        write_blob(self.connection, self.hash, &self.file)?;
+        write_attachments(self.connection, &self.card, &self.folder, &self.config)?;
+        return Ok(());
+    }
+}
+
+impl<'a> Checkout<'a> {
+    pub fn new(connection: &'a Connection, folder: &Path) -> Result<Checkout<'a>, String> {
+        if folder.exists() {
+            let mut entries = fs::read_dir(folder).map_err(|_| format!("Fail to read {}", folder.to_string_lossy()))?;
+            if entries.next().is_some() {
+                return Err(format!("The folder {} is not empty", folder.to_string_lossy()));
+            }
+        }
+        Ok(Checkout { connection, folder: folder.to_path_buf(), config: carddb::card_config(connection) })
+    }
+
+    /// Write the cards and return how many of them were written.
+    pub fn call_once(self) -> Result<usize, String> {
+        fs::create_dir_all(&self.folder).map_err(|_| format!("Fail to create {}", self.folder.to_string_lossy()))?;
+
+        let cards = content::all_timeline_cards(self.connection);
+        for card in &cards {
+            let hash = hash::Hash::from_text(&card.content_sha256)
+                .map_err(|msg| format!("{}: {}", card.name(), msg))?;
+            let file = card_file(self.connection, &self.folder, &self.config, &card.face, &hash);
+            write_blob(self.connection, hash, &file)?;
+            write_attachments(self.connection, &card.face, &self.folder, &self.config)?;
+        }
+        return Ok(cards.len());
+    }
+}
+
+/// The file of the card in the folder. A new file gets the slug of the
+/// content if the file pattern has one.
+fn card_file(conn: &Connection, folder: &Path, config: &CardConfig, card: &Face, hash: &hash::Hash) -> PathBuf {
+    let file = card.location_in(folder, config);
+    if file.exists() || !config.pattern.has_slug() {
+        return file;
+    }
+    return match blob::load(conn, hash.clone()) {
+        Some(content) => card.new_location_in(folder, config, &String::from_utf8_lossy(&content)),
+        None => file,
+    };
+}
+
+fn write_blob(conn: &Connection, hash: hash::Hash, file: &Path) -> Result<(), String> {
+    if !blob::exists(conn, &hash) {
+        return Err(format!("The content {} is missing from the timeline", hash));
+    }
+
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent).map_err(|_| format!("Fail to create {}", parent.to_string_lossy()))?;
+    }
+    blob::load_to_file(conn, &hash, file)?;
+    return Ok(());
+}
+
+/// Write the attachments of the card into a folder next to the card file.
+/// Return how many were written.
+pub fn write_attachments(conn: &Connection, card: &Face, folder: &Path, config: &CardConfig) -> Result<usize, String> {
+    let attachments = attachment::attachments_of_card(conn, card)?;
+    if attachments.len() == 0 {
+        return Ok(0);
+    }
+
+    let dir = card.attachments_in(folder, config);
+    fs::create_dir_all(&dir).map_err(|_| format!("Fail to create {}", dir.to_string_lossy()))?;
+
+    for attachment in &attachments {
+        // Never write outside of the attachment folder whatever the stored name is.
+        let file_name = Path::new(&attachment.file_name).file_name();
+        if file_name.is_none() {
+            return Err(format!("Invalid attachment name {}", attachment.file_name));
+        }
+        let file = dir.join(file_name.unwrap());
+        let hash = hash::Hash::from_text(&attachment.content_sha256)
+            .map_err(|msg| format!("{}: {}", attachment.file_name, msg))?;
+        write_blob(conn, hash, &file)?;
+    }
+    return Ok(attachments.len());
+}
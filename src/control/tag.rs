@@ -1,4 +1,4 @@
-use rusqlite::{Connection, params};
+use rusqlite::Connection;
 use crate::model::tag::TagFeature as TagFeature;
 use crate::card;
 
@@ -26,11 +26,14 @@ pub struct DeleteTagAll<'a> {
     tag_name: String
 }
 
+// Note(wistrandj): The tag history batches are not in any command yet.
+#[allow(dead_code)]
 pub struct CreateTagHistoryBatch<'a> {
     connection: &'a Connection,
     batch_id: usize,
 }
 
+#[allow(dead_code)]
 pub struct DropLatestTagHistoryBatch<'a> {
     connection: &'a Connection,
     batch_id: usize,
@@ -99,7 +102,7 @@ impl<'a> SetTag<'a> {
         }
     }
 
-    pub fn call_once(self, args: ()) -> Result<(), &'static str> {
+    pub fn call_once(self, _args: ()) -> Result<(), &'static str> {
         let feat = TagFeature::new(self.connection);
         return feat.set_tag_to_card(&self.tag_name, self.major_card_number);
     }
@@ -125,7 +128,7 @@ impl<'a> DeleteTag<'a> {
 
 impl<'a> DeleteTagAll<'a> {
     pub fn new(connection: &'a Connection, tag_name: &str) -> Result<DeleteTagAll<'a>, &'static str> {
-        let feat = TagFeature::new(&connection);
+        let feat = TagFeature::new(connection);
         let tag_exists = feat.tag_exists(tag_name)?;
         if tag_exists {
             return Ok(DeleteTagAll {
@@ -143,6 +146,7 @@ impl<'a> DeleteTagAll<'a> {
     }
 }
 
+#[allow(dead_code)]
 impl<'a> CreateTagHistoryBatch<'a> {
     pub fn new(connection: &'a Connection) -> Result<CreateTagHistoryBatch<'a>, &'static str> {
         let feat = TagFeature::new(connection);
@@ -159,6 +163,7 @@ impl<'a> CreateTagHistoryBatch<'a> {
     }
 }
 
+#[allow(dead_code)]
 impl<'a> DropLatestTagHistoryBatch<'a> {
     pub fn new(connection: &'a Connection) -> Result<DropLatestTagHistoryBatch<'a>, &'static str> {
         let feat = TagFeature::new(connection);
@@ -0,0 +1,252 @@
+// Line based diff of two texts in the unified format.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Same,
+    Removed,
+    Added,
+}
+
+pub struct Line<'a> {
+    pub edit: Edit,
+    pub text: &'a str,
+}
+
+pub struct Hunk<'a> {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<&'a Line<'a>>,
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Shortest edit from old lines to new lines by the longest common subsequence.
+pub fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    // Cards are short. Trim the common head and tail and run the quadratic
+    // algorithm only on the part that changed.
+    let mut head = 0;
+    while head < old.len() && head < new.len() && old[head] == new[head] {
+        head += 1;
+    }
+    let mut tail = 0;
+    while tail < old.len() - head && tail < new.len() - head
+        && old[old.len() - 1 - tail] == new[new.len() - 1 - tail] {
+        tail += 1;
+    }
+
+    let old_mid = &old[head..old.len() - tail];
+    let new_mid = &new[head..new.len() - tail];
+    let n = old_mid.len();
+    let m = new_mid.len();
+
+    // lcs[i][j] is the length of the common subsequence of old_mid[i..] and new_mid[j..].
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    for text in &old[..head] {
+        lines.push(Line { edit: Edit::Same, text });
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_mid[i] == new_mid[j] {
+            lines.push(Line { edit: Edit::Same, text: old_mid[i] });
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            lines.push(Line { edit: Edit::Added, text: new_mid[j] });
+            j += 1;
+        } else {
+            lines.push(Line { edit: Edit::Removed, text: old_mid[i] });
+            i += 1;
+        }
+    }
+
+    for text in &old[old.len() - tail..] {
+        lines.push(Line { edit: Edit::Same, text });
+    }
+
+    // Removed lines read better before added lines within a change.
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        while end < lines.len() && lines[end].edit != Edit::Same {
+            end += 1;
+        }
+        lines[start..end].sort_by_key(|line| line.edit == Edit::Added);
+        start = end + 1;
+    }
+
+    return lines;
+}
+
+/// Group the changed lines into hunks with the given lines of context around them.
+pub fn hunks<'a>(lines: &'a [Line<'a>], context: usize) -> Vec<Hunk<'a>> {
+    let changes: Vec<usize> = lines.iter().enumerate()
+        .filter(|(_, line)| line.edit != Edit::Same)
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for i in changes {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(lines.len());
+        if let Some(last) = ranges.last_mut() {
+            if start <= last.1 {
+                last.1 = end;
+                continue;
+            }
+        }
+        ranges.push((start, end));
+    }
+
+    let mut result = Vec::new();
+    for (start, end) in ranges {
+        let old_before = lines[..start].iter().filter(|line| line.edit != Edit::Added).count();
+        let new_before = lines[..start].iter().filter(|line| line.edit != Edit::Removed).count();
+        let old_len = lines[start..end].iter().filter(|line| line.edit != Edit::Added).count();
+        let new_len = lines[start..end].iter().filter(|line| line.edit != Edit::Removed).count();
+
+        // An empty range points at the line before it, like diff(1) does.
+        result.push(Hunk {
+            old_start: if old_len == 0 { old_before } else { old_before + 1 },
+            old_len,
+            new_start: if new_len == 0 { new_before } else { new_before + 1 },
+            new_len,
+            lines: lines[start..end].iter().collect(),
+        });
+    }
+
+    return result;
+}
+
+/// Unified diff of two texts or None if they are the same.
+pub fn unified(old_name: &str, new_name: &str, old: &str, new: &str, color: bool) -> Option<String> {
+    // Note(wistrandj): The lines keep their newline so that a last line
+    // without one differs from the same line with one.
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let lines = diff_lines(&old_lines, &new_lines);
+    let hunks = hunks(&lines, 3);
+
+    if hunks.len() == 0 {
+        return None;
+    }
+
+    let paint = |code: &str, text: String| -> String {
+        if color { format!("{}{}{}", code, text, RESET) } else { text }
+    };
+
+    let mut out = String::new();
+    out.push_str(&paint(BOLD, format!("--- {}", old_name)));
+    out.push('\n');
+    out.push_str(&paint(BOLD, format!("+++ {}", new_name)));
+    out.push('\n');
+
+    for hunk in hunks {
+        let header = format!("@@ -{},{} +{},{} @@", hunk.old_start, hunk.old_len, hunk.new_start, hunk.new_len);
+        out.push_str(&paint(CYAN, header));
+        out.push('\n');
+        for line in hunk.lines {
+            let text = line.text.strip_suffix('\n');
+            let content = text.unwrap_or(line.text);
+            let content = content.strip_suffix('\r').unwrap_or(content);
+            let content = match line.edit {
+                Edit::Same => format!(" {}", content),
+                Edit::Removed => paint(RED, format!("-{}", content)),
+                Edit::Added => paint(GREEN, format!("+{}", content)),
+            };
+            out.push_str(&content);
+            out.push('\n');
+            if text.is_none() {
+                out.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    return Some(out);
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_same() {
+        assert!(unified("a", "b", "one\ntwo\n", "one\ntwo\n", false).is_none());
+        assert!(unified("a", "b", "", "", false).is_none());
+    }
+
+    #[test]
+    fn test_change() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\n";
+        let patch = unified("a", "b", old, new, false).unwrap();
+        let expected = "\
+--- a
++++ b
+@@ -2,8 +2,9 @@
+ 2
+ 3
+ 4
+-5
++five
+ 6
+ 7
+ 8
+ 9
++10
+";
+        assert_eq!(patch, expected);
+    }
+
+    #[test]
+    fn test_separate_hunks_and_empty_sides() {
+        let old: Vec<String> = (1..=20).map(|i| i.to_string()).collect();
+        let mut new = old.clone();
+        new[0] = String::from("one");
+        new[19] = String::from("twenty");
+        let patch = unified("a", "b", &old.join("\n"), &new.join("\n"), false).unwrap();
+        assert_eq!(patch.matches("@@ -").count(), 2);
+        assert!(patch.contains("@@ -1,4 +1,4 @@"));
+        assert!(patch.contains("@@ -17,4 +17,4 @@"));
+
+        let patch = unified("a", "b", "", "new\n", false).unwrap();
+        assert!(patch.contains("@@ -0,0 +1,1 @@\n+new\n"));
+    }
+
+    #[test]
+    fn test_trailing_newline() {
+        let patch = unified("a", "b", "one\ntwo", "one\ntwo\n", false).unwrap();
+        let expected = "\
+--- a
++++ b
+@@ -1,2 +1,2 @@
+ one
+-two
+\\ No newline at end of file
++two
+";
+        assert_eq!(patch, expected);
+
+        let patch = unified("a", "b", "one\n", "one", false).unwrap();
+        assert!(patch.ends_with("-one\n+one\n\\ No newline at end of file\n"));
+        assert!(unified("a", "b", "one", "one", false).is_none());
+    }
+}
@@ -2,14 +2,28 @@ use rusqlite::{Connection, params};
 
 pub trait Feature {
     fn enable(&self, conn: &mut Connection);
+    #[allow(dead_code)]
     fn rollback(&self, conn: &mut Connection);
 }
 
 pub fn enable_feature(name: &str, conn: &mut Connection, feature: &dyn Feature) {
+    if has(name, conn) {
+        return;
+    }
+
     feature.enable(conn);
+
+    let success = conn.execute(
+        "insert into feature(feature_name) values (?1);",
+        params![name]
+    );
+
+    if let Err(msg) = success {
+        panic!("Fail to register feature {}. Reason: {}", name, msg);
+    }
 }
 
-pub fn has_feature_table(name: &str, conn: &Connection) -> bool {
+pub fn has_feature_table(_name: &str, conn: &Connection) -> bool {
     let row = conn.query_row(
         "select count(*) from sqlite_master where type = 'table' and name = 'feature'",
         params![],
@@ -21,7 +35,7 @@ pub fn has_feature_table(name: &str, conn: &Connection) -> bool {
     );
 
     return match row {
-        Ok(number) => (number == 1),
+        Ok(number) => number == 1,
         Err(_) => { panic!("Fail to read sqlite_master table"); }
     }
 }
@@ -29,7 +43,7 @@ pub fn has_feature_table(name: &str, conn: &Connection) -> bool {
 pub fn has(name: &str, conn: &Connection) -> bool {
     let row = conn.query_row(
         "select count(*) from feature where feature_name = ?1;",
-        params![],
+        params![name],
         |row| {
             let count_of_tables: u32 = row.get(0)?;
             let count_of_tables: usize = count_of_tables as usize;
@@ -38,7 +52,7 @@ pub fn has(name: &str, conn: &Connection) -> bool {
     );
 
     return match row {
-        Ok(number) => (number == 1),
+        Ok(number) => number == 1,
         Err(_) => { panic!("Fail to read features"); }
     }
 }
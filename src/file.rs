@@ -9,9 +9,9 @@ pub fn make_template(path: &Path) {
     let now = SystemTime::now();
     let date: DateTime<Local> = DateTime::from(now);
     let date_template = format!("{}\n\n\n", date.format("%Y-%m-%d"));
-    let success = fs::write(&path, date_template);
+    let success = fs::write(path, date_template);
 
-    if let Err(_) = success {
+    if success.is_err() {
         eprintln!("Failed to write template {}", path.to_str().unwrap());
     }
 }
@@ -1,80 +1,180 @@
-use sha2;
 use sha2::Digest;
 use std::path;
+use std::fmt;
 use std::fs;
 use std::io;
 use std::cmp::{Eq, PartialEq};
+use std::str::FromStr;
 
-const Sha256_size: usize = 32;
+/// Files and blobs are read and written in chunks of this size so that the
+/// memory use does not depend on the size of the file.
+pub const CHUNK_SIZE: usize = 64 * 1024;
 
-#[derive(std::hash::Hash, Eq, PartialEq)]
+/// The function a hash is calculated with. A timeline has a default
+/// algorithm for new content but may hold hashes of several algorithms.
+#[derive(std::hash::Hash, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Algorithm {
+    Sha256,
+    Blake3,
+}
+
+impl Algorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Blake3 => "blake3",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Algorithm, &'static str> {
+        match name {
+            "sha256" => Ok(Algorithm::Sha256),
+            "blake3" => Ok(Algorithm::Blake3),
+            _ => Err("Unknown hash algorithm. Use sha256 or blake3"),
+        }
+    }
+
+    pub fn digest_size(&self) -> usize {
+        match self {
+            Algorithm::Sha256 => 32,
+            Algorithm::Blake3 => blake3::OUT_LEN,
+        }
+    }
+}
+
+/// A hash that knows its algorithm. The text form is "<algorithm>:<hex>",
+/// like "blake3:af13...", except that a SHA-256 hash is plain hex. That is
+/// how the content of every timeline was identified before the other
+/// algorithms and those identifiers must not change.
+#[derive(std::hash::Hash, Eq, PartialEq, Clone, Debug)]
 pub struct Hash {
-    bytes: [u8; Sha256_size]
+    algorithm: Algorithm,
+    bytes: Vec<u8>,
+}
+
+impl FromStr for Hash {
+    type Err = String;
+
+    fn from_str(slice: &str) -> Result<Hash, String> {
+        return Self::from_text(slice);
+    }
 }
 
 impl Hash {
     pub fn from_sha2_hasher(hasher: sha2::Sha256) -> Hash {
         let digest = hasher.finalize();
-        return Self::from_raw_hash(&digest.as_slice());
+        return Self::from_raw_hash(Algorithm::Sha256, digest.as_slice());
     }
 
-    // fn from_sha2_digest(digest: sha2::Digest<OutputSize=u32>) -> Hash {
-    //     panic!("Not implemented");
-    // }
+    /// Parse the textual representation given by to_string(..). Accept the
+    /// "sha256:" prefix too.
+    pub fn from_text(slice: &str) -> Result<Hash, String> {
+        let (algorithm, hex) = split_algorithm(slice)?;
+        let bytes = hexslice(hex, algorithm)?;
+        return Ok(Hash { algorithm, bytes });
+    }
 
-    pub fn from_text(slice: &str) -> Hash {
-        if slice.len() != 2 * Sha256_size {
-            panic!("Textal representation of a hash hash invalid size of {} instead of {}", slice.len(), 2 * Sha256_size);
+    pub fn from_raw_hash(algorithm: Algorithm, slice: &[u8]) -> Hash {
+        if slice.len() != algorithm.digest_size() {
+            panic!("Invalid digest size. Expect {} but found {}", algorithm.digest_size(), slice.len());
         }
-
-        let mut raw_hash = [0u8; Sha256_size];
-        let mut slice_it = slice.bytes().into_iter();
-
-        for next in raw_hash.iter_mut() {
-            let hi: u8 = slice_it.next().unwrap();
-            let lo: u8 = slice_it.next().unwrap();
-            *next = hexcharvalue(hi, lo);
+        Hash {
+            algorithm,
+            bytes: slice.to_vec(),
         }
+    }
 
-        return Self::from_raw_hash(&raw_hash);
+    pub fn algorithm(&self) -> Algorithm {
+        return self.algorithm;
     }
 
-    pub fn from_raw_hash(slice: &[u8]) -> Hash {
-        // Note(wistrandj): Change the type to slice of 32 bytes.
-        let mut hash: [u8; Sha256_size] = [0; Sha256_size];
+    /// The digest in hex without the algorithm.
+    pub fn hex(&self) -> String {
+        return hexstring(&self.bytes);
+    }
 
-        let mut i = 0;
-        for b in slice {
-            hash[i] = *b;
-            i = i + 1;
+    pub fn file(file: &path::Path, algorithm: Algorithm) -> Result<Hash, io::Error> {
+        let mut file = fs::File::open(file)?;
+        return Self::reader(&mut file, algorithm);
+    }
+
+    /// Hash everything from the reader in chunks of CHUNK_SIZE bytes.
+    pub fn reader(reader: &mut dyn io::Read, algorithm: Algorithm) -> Result<Hash, io::Error> {
+        let mut hasher = Hasher::new(algorithm);
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        loop {
+            let count = reader.read(&mut buffer)?;
+            if count == 0 {
+                break;
+            }
+            hasher.update(&buffer[..count]);
         }
+        return Ok(hasher.finish());
+    }
+}
 
-        if i != Sha256_size {
-            // Note(wistrandj): The sha2 library provides GenericArray
-            // which includes the information about hash size. This function should
-            // use that type information and consume a Digest instead
-            // of sha2::Sha256.
-            panic!("Invalid digest size. Expect 32 but found {}", i);
+/// The hex digits with the algorithm as a prefix like blake3:. SHA-256 has
+/// no prefix.
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.algorithm {
+            Algorithm::Sha256 => write!(f, "{}", self.hex()),
+            _ => write!(f, "{}:{}", self.algorithm.name(), self.hex()),
         }
+    }
+}
 
-        Hash {
-            bytes: hash
+/// Split a hash or a hash prefix into its algorithm and hex digits. Plain
+/// hex is SHA-256.
+pub fn split_algorithm(slice: &str) -> Result<(Algorithm, &str), &'static str> {
+    match slice.find(':') {
+        Some(colon) => Ok((Algorithm::from_name(&slice[..colon])?, &slice[colon + 1..])),
+        None => Ok((Algorithm::Sha256, slice)),
+    }
+}
+
+enum HasherState {
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+/// Calculate a hash piece by piece while the data is read or written.
+pub struct Hasher {
+    state: HasherState,
+}
+
+impl Hasher {
+    pub fn new(algorithm: Algorithm) -> Hasher {
+        let state = match algorithm {
+            Algorithm::Sha256 => HasherState::Sha256(sha2::Sha256::new()),
+            Algorithm::Blake3 => HasherState::Blake3(Box::new(blake3::Hasher::new())),
+        };
+        Hasher { state }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match &mut self.state {
+            HasherState::Sha256(hasher) => { hasher.update(data); },
+            HasherState::Blake3(hasher) => { hasher.update(data); },
         }
     }
 
-    pub fn from_str(slice: &str) -> Hash {
-        panic!("NIY");
+    pub fn finish(self) -> Hash {
+        match self.state {
+            HasherState::Sha256(hasher) => Hash::from_sha2_hasher(hasher),
+            HasherState::Blake3(hasher) => Hash::from_raw_hash(Algorithm::Blake3, hasher.finalize().as_bytes()),
+        }
     }
+}
 
-    pub fn to_string(&self) -> String {
-        return hexstring(&self.bytes);
+impl io::Write for Hasher {
+    fn write(&mut self, data: &[u8]) -> Result<usize, io::Error> {
+        self.update(data);
+        return Ok(data.len());
     }
 
-    pub fn file(file: &path::Path) -> Result<Hash, io::Error> {
-        let mut hasher = sha2::Sha256::new();
-        let content = fs::read(file)?;
-        hasher.update(content);
-        return Ok(Self::from_sha2_hasher(hasher));
+    fn flush(&mut self) -> Result<(), io::Error> {
+        return Ok(());
     }
 }
 
@@ -87,10 +187,15 @@ fn hexchar(c: u8) -> [char; 2] {
     return [hi, lo];
 }
 
-fn hexcharvalue(hi: u8, lo: u8) -> u8 {
-    // Note: Opposite of hexchar(..)
+fn hexcharvalue(hi: u8, lo: u8) -> Result<u8, &'static str> {
+    // Note: Opposite of hexchar(..). Accept upper case letters too.
     let set = b"0123456789abcdef";
-    panic!("NIY");
+    let hi = set.iter().position(|c| *c == hi.to_ascii_lowercase());
+    let lo = set.iter().position(|c| *c == lo.to_ascii_lowercase());
+    match (hi, lo) {
+        (Some(hi), Some(lo)) => Ok(((hi << 4) | lo) as u8),
+        _ => Err("Invalid hex character in a hash"),
+    }
 }
 
 fn hexstring(slice: &[u8]) -> String {
@@ -103,12 +208,24 @@ fn hexstring(slice: &[u8]) -> String {
     return s;
 }
 
-fn hexslice(slice: &str) -> [u8; Sha256_size] {
-    // Note(wistradj): This len() is in bytes, right?
-    if slice.len() != 2 * Sha256_size {
-        panic!("The slice is not exactly 64 bytes");
+fn hexslice(slice: &str, algorithm: Algorithm) -> Result<Vec<u8>, String> {
+    // Note(wistradj): This len() is in bytes, right? Yes. A multibyte
+    // character is rejected below as it's not a hex character.
+    let size = algorithm.digest_size();
+    if slice.len() != 2 * size {
+        return Err(format!("A {} hash must be exactly {} hex characters", algorithm.name(), 2 * size));
     }
-    return [0; Sha256_size]
+
+    let mut raw_hash = vec![0u8; size];
+    let mut slice_it = slice.bytes();
+
+    for next in raw_hash.iter_mut() {
+        let hi: u8 = slice_it.next().unwrap();
+        let lo: u8 = slice_it.next().unwrap();
+        *next = hexcharvalue(hi, lo)?;
+    }
+
+    return Ok(raw_hash);
 }
 
 // fn main() {
@@ -130,3 +247,47 @@ fn hexslice(slice: &str) -> [u8; Sha256_size] {
 //     let hash = Hash::file(&path::PathBuf::from("./Cargo.toml")).unwrap();
 //     println!("{}", hash.to_string());
 // }
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_text_round_trip() {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(b"helloworld");
+        let hash = Hash::from_sha2_hasher(hasher);
+        let text = hash.to_string();
+        assert_eq!(text, "936a185caaa266bb9cbe981e9e05cb78cd732b0b3280eb944412bb6f8f8f07af");
+        assert_eq!(Hash::from_text(&text), Ok(hash.clone()));
+        assert_eq!(text.to_uppercase().parse::<Hash>(), Ok(hash.clone()));
+        assert_eq!(Hash::from_text(&format!("sha256:{}", text)), Ok(hash));
+    }
+
+    #[test]
+    fn test_algorithms() {
+        let sha256 = Hash::reader(&mut &b"helloworld"[..], Algorithm::Sha256).unwrap();
+        assert_eq!(sha256.to_string(), "936a185caaa266bb9cbe981e9e05cb78cd732b0b3280eb944412bb6f8f8f07af");
+
+        let blake3 = Hash::reader(&mut &b"helloworld"[..], Algorithm::Blake3).unwrap();
+        let text = blake3.to_string();
+        assert!(text.starts_with("blake3:"));
+        assert_eq!(text.len(), "blake3:".len() + 64);
+        assert_eq!(blake3.algorithm(), Algorithm::Blake3);
+        assert_eq!(Hash::from_text(&text), Ok(blake3.clone()));
+        assert_ne!(blake3.hex(), sha256.hex());
+        assert!(Hash::from_text("md5:936a185caaa266bb9cbe981e9e05cb78cd732b0b3280eb944412bb6f8f8f07af").is_err());
+    }
+
+    #[test]
+    fn test_invalid_text() {
+        assert!(Hash::from_text("").is_err());
+        assert!(Hash::from_text("936a").is_err());
+        let not_hex = "x36a185caaa266bb9cbe981e9e05cb78cd732b0b3280eb944412bb6f8f8f07af";
+        assert!(Hash::from_text(not_hex).is_err());
+        let multibyte = "ä6a185caaa266bb9cbe981e9e05cb78cd732b0b3280eb944412bb6f8f8f07a";
+        assert!(Hash::from_text(multibyte).is_err());
+        assert_eq!(Hash::from_text("blake3:936a"), Err(String::from("A blake3 hash must be exactly 64 hex characters")));
+    }
+}
@@ -1,3 +1,8 @@
+// The code base spells out `return` statements and length checks on
+// purpose. Keep clippy focused on the rest.
+#![allow(clippy::needless_return)]
+#![allow(clippy::len_zero)]
+
 mod file;
 mod varg;
 use varg::Args;
@@ -12,6 +17,21 @@ mod feature;
 mod hash;
 mod zkblob;
 mod zktag;
+mod zkstatus;
+mod zkcommit;
+mod zklog;
+mod zkls;
+mod zkdiff;
+mod zkrestore;
+mod zkgc;
+mod zkfsck;
+mod zkattach;
+mod zknav;
+mod zktree;
+mod zkmv;
+mod zkrelayout;
+mod zkcheckignore;
+mod diff;
 
 fn main() {
     let args = Args::from_user_args();
@@ -26,13 +46,79 @@ fn main() {
                     zkset::zkset(timeline_file, &args.args);
                 },
                 "card" => {
-                    zkcard::zkcard(timeline_file);
+                    zkcard::zkcard(timeline_file, &args);
                 },
                 "blob" => {
                     zkblob::zkblob(timeline_file, &args);
                 },
                 "tag" => {
-                    zktag::zktag(timeline_file, &args);
+                    if let Err(msg) = zktag::zktag(timeline_file, &args) {
+                        eprintln!("{}", msg);
+                        std::process::exit(1);
+                    }
+                },
+                "status" => {
+                    zkstatus::zkstatus(timeline_file, &args);
+                },
+                "commit" => {
+                    zkcommit::zkcommit(timeline_file, &args);
+                },
+                "log" => {
+                    zklog::zklog(timeline_file, &args);
+                },
+                "ls" => {
+                    zkls::zkls(timeline_file, &args);
+                },
+                "diff" => {
+                    let status = zkdiff::zkdiff(timeline_file, &args);
+                    std::process::exit(status);
+                },
+                "restore" => {
+                    zkrestore::zkrestore(timeline_file, &args);
+                },
+                "checkout" => {
+                    zkrestore::zkcheckout(timeline_file, &args);
+                },
+                "gc" => {
+                    zkgc::zkgc(timeline_file, &args);
+                },
+                "fsck" => {
+                    let status = zkfsck::zkfsck(timeline_file, &args);
+                    std::process::exit(status);
+                },
+                "attach" => {
+                    zkattach::zkattach(timeline_file, &args);
+                },
+                "attachments" => {
+                    zkattach::zkattachments(timeline_file, &args);
+                },
+                "detach" => {
+                    zkattach::zkdetach(timeline_file, &args);
+                },
+                "parent" => {
+                    let status = zknav::zkparent(timeline_file, &args);
+                    std::process::exit(status);
+                },
+                "children" => {
+                    let status = zknav::zkchildren(timeline_file, &args);
+                    std::process::exit(status);
+                },
+                "next" => {
+                    let status = zknav::zknext(timeline_file, &args);
+                    std::process::exit(status);
+                },
+                "tree" => {
+                    zktree::zktree(timeline_file, &args);
+                },
+                "mv" => {
+                    zkmv::zkmv(timeline_file, &args);
+                },
+                "relayout" => {
+                    zkrelayout::zkrelayout(timeline_file, &args);
+                },
+                "check-ignore" => {
+                    let status = zkcheckignore::zkcheckignore(timeline_file, &args);
+                    std::process::exit(status);
                 },
                 _ => {
                     eprintln!("Invalid or missing subcommand");
@@ -0,0 +1,105 @@
+use rusqlite::{Connection, params};
+use crate::card::Face;
+
+pub struct Attachment {
+    pub file_name: String,
+    pub mime_type: String,
+    pub content_sha256: String,
+}
+
+/// Attach content to a card. An attachment with the same file name is replaced.
+pub fn attach(conn: &Connection, card: &Face, file_name: &str, mime_type: &str, content_sha256: &str) -> Result<(), &'static str> {
+    let sql = "insert or replace into attachment(card_name, file_name, mime_type, content_sha256) values (?1, ?2, ?3, ?4);";
+    let args = params![card.name(), file_name, mime_type, content_sha256];
+    match conn.execute(sql, args) {
+        Ok(_) => Ok(()),
+        Err(_) => Err("Fail to attach the file"),
+    }
+}
+
+/// Remove an attachment. Return false if the card has no such attachment.
+/// The content stays in the timeline until garbage collected.
+pub fn detach(conn: &Connection, card: &Face, file_name: &str) -> Result<bool, &'static str> {
+    let sql = "delete from attachment where card_name = ?1 and file_name = ?2;";
+    match conn.execute(sql, params![card.name(), file_name]) {
+        Ok(count) => Ok(count > 0),
+        Err(_) => Err("Fail to detach the file"),
+    }
+}
+
+pub fn attachments_of_card(conn: &Connection, card: &Face) -> Result<Vec<Attachment>, &'static str> {
+    let sql = "
+        select file_name, mime_type, content_sha256
+        from attachment
+        where card_name = ?1
+        order by file_name;";
+    let stmt = conn.prepare(sql);
+    if stmt.is_err() {
+        return Err("Fail to prepare a query");
+    }
+    let mut stmt = stmt.unwrap();
+
+    let rows = stmt.query_map(params![card.name()], |row| {
+        Ok(Attachment {
+            file_name: row.get(0)?,
+            mime_type: row.get(1)?,
+            content_sha256: row.get(2)?,
+        })
+    });
+    if rows.is_err() {
+        return Err("Fail to read attachments");
+    }
+
+    let mut attachments = Vec::new();
+    for row in rows.unwrap() {
+        if let Ok(attachment) = row {
+            attachments.push(attachment);
+        } else {
+            return Err("Fail to read attachments");
+        }
+    }
+    return Ok(attachments);
+}
+
+/// Guess the mime type from the file extension.
+pub fn guess_mime_type(file_name: &str) -> &'static str {
+    let extension = file_name.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+    if !file_name.contains('.') {
+        return "application/octet-stream";
+    }
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "zip" => "application/zip",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_guess_mime_type() {
+        assert_eq!(guess_mime_type("diagram.png"), "image/png");
+        assert_eq!(guess_mime_type("Scan.PDF"), "application/pdf");
+        assert_eq!(guess_mime_type("archive.tar.gz"), "application/octet-stream");
+        assert_eq!(guess_mime_type("png"), "application/octet-stream");
+    }
+}
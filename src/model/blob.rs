@@ -1,13 +1,40 @@
-use rusqlite::{Connection, params};
-use crate::card::Face;
-use std::path::Path;
+use rusqlite::{Connection, DatabaseName, params};
+use rusqlite::blob::ZeroBlob;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::io::{Read, Write};
 use crate::hash;
+use crate::card::Timestamp;
+use crate::model::carddb;
+use crate::model::codec::{Codec, Counter};
 
-pub fn save(conn: &Connection, file: &Path) -> hash::Hash {
-    // Todo(wistrandj): Read content and calculate hash at the same time.
-    let hash = hash::Hash::file(file).unwrap();
-    let hash_str = hash.to_string();
+/// Where the content of a new blob is read from. It is opened twice: once
+/// to measure it and once to store it.
+pub type Source<'a> = dyn Fn() -> Result<Box<dyn Read + 'a>, String> + 'a;
+
+/// The hash and the sizes of the content before it is stored.
+pub struct Measure {
+    pub hash: hash::Hash,
+    pub size: u64,
+    pub encoded_size: u64,
+}
+
+pub fn save(conn: &Connection, file: &Path) -> Result<hash::Hash, &'static str> {
+    let source = || -> Result<Box<dyn Read>, String> {
+        let input = fs::File::open(file).map_err(|_| String::from("Fail to read the file"))?;
+        Ok(Box::new(input))
+    };
+
+    // The hash is needed before the content to skip blobs stored already.
+    // So the file is read twice, but only one chunk at a time. The first
+    // read also tells whether compressing the content pays off.
+    let codec = carddb::codec(conn);
+    let measure = measure(&source, codec, carddb::hash_algorithm(conn));
+    if measure.is_err() {
+        return Err("Fail to read the file");
+    }
+    let measure = measure.unwrap();
+    let hash_str = measure.hash.to_string();
     let content_available = conn.query_row(
         "select count(*) from content where content_sha256 = ?1;",
         params![hash_str],
@@ -20,36 +47,742 @@ pub fn save(conn: &Connection, file: &Path) -> hash::Hash {
 
     if let Ok(count_rows) = content_available {
         if count_rows == 0 {
-            let blob = fs::read(file).unwrap();
-            let success = conn.execute(
-                "insert into content(content_sha256, blob) values (?1, ?2);",
-                params![hash_str, blob],
-            );
+            let sidecar = carddb::sidecar_threshold(conn).is_some_and(|threshold| measure.size > threshold);
+            write_new(conn, &source, &measure, codec, sidecar)?;
+        }
+    } else {
+        return Err("Content table missing");
+    }
+
+    return Ok(measure.hash);
+}
+
+/// Hash the content and count its size before and after encoding.
+pub fn measure(source: &Source, codec: Codec, algorithm: hash::Algorithm) -> Result<Measure, String> {
+    let mut input = source()?;
+    let mut hasher = hash::Hasher::new(algorithm);
+    let mut counter = Counter::sink();
+    let mut encoder = codec.encoder(&mut counter);
+    let mut buffer = vec![0u8; hash::CHUNK_SIZE];
+    let mut size: u64 = 0;
+    loop {
+        let count = input.read(&mut buffer)
+            .map_err(|msg| format!("Fail to read the content. Reason: {}", msg))?;
+        if count == 0 {
+            break;
+        }
+        hasher.update(&buffer[..count]);
+        encoder.write_all(&buffer[..count])
+            .map_err(|msg| format!("Fail to encode the content. Reason: {}", msg))?;
+        size += count as u64;
+    }
+    encoder.finish().map_err(|msg| format!("Fail to encode the content. Reason: {}", msg))?;
+
+    return Ok(Measure { hash: hasher.finish(), size, encoded_size: counter.count });
+}
+
+/// Store new content with the codec if it makes the content smaller, and
+/// either in the timeline file or in the objects directory. Return the rowid.
+pub fn write_new(conn: &Connection, source: &Source, measure: &Measure, codec: Codec, sidecar: bool) -> Result<i64, &'static str> {
+    let codec = choose_codec(measure, codec);
+    let stored_size = if codec == Codec::Identity { measure.size } else { measure.encoded_size };
+
+    if let (true, Some(objects)) = (sidecar, objects_dir(conn)) {
+        return write_new_object(conn, source, &measure.hash, codec, &objects);
+    }
+    return write_new_blob(conn, source, &measure.hash, codec, stored_size);
+}
+
+/// The codec to store the measured content with. Content that does not get
+/// smaller, like a photo, is stored as is.
+pub fn choose_codec(measure: &Measure, codec: Codec) -> Codec {
+    if codec != Codec::Identity && measure.encoded_size < measure.size {
+        return codec;
+    }
+    return Codec::Identity;
+}
+
+/// Insert a zero filled blob of the encoded size and write the encoded
+/// content into it with the incremental blob I/O of sqlite.
+fn write_new_blob(conn: &Connection, source: &Source, hash: &hash::Hash, codec: Codec, stored_size: u64) -> Result<i64, &'static str> {
+    let input = source();
+    if input.is_err() {
+        return Err("Fail to read the file");
+    }
+    let mut input = input.unwrap();
+    if stored_size > i32::MAX as u64 {
+        return Err("The file is too large to be stored in the timeline");
+    }
+
+    let success = conn.execute(
+        "insert into content(content_sha256, blob, stored_time, codec) values (?1, ?2, ?3, ?4);",
+        params![hash.to_string(), ZeroBlob(stored_size as i32), Timestamp::now().to_string(), codec.name()],
+    );
+    if let Err(msg) = success {
+        eprintln!("Fail to save blob content to timeline file. Reason: {}", msg);
+        return Err("Fail to save blob content");
+    }
+
+    // Do not leave a half written blob behind.
+    let rowid = conn.last_insert_rowid();
+    let success = copy_to_blob(conn, &mut input, codec, stored_size, rowid, hash);
+    if let Err(msg) = success {
+        let _ = conn.execute("delete from content where rowid = ?1;", params![rowid]);
+        return Err(msg);
+    }
+    return Ok(rowid);
+}
+
+fn copy_to_blob(conn: &Connection, input: &mut dyn Read, codec: Codec, stored_size: u64, rowid: i64, hash: &hash::Hash) -> Result<(), &'static str> {
+    let output = conn.blob_open(DatabaseName::Main, "content", "blob", rowid, false);
+    if output.is_err() {
+        return Err("Fail to open the blob for writing");
+    }
+    let mut output = output.unwrap();
+    let written = copy_verified(input, codec, Some(stored_size), &mut output, hash)?;
+    if written != stored_size {
+        return Err("The file changed while it was saved");
+    }
+    return Ok(());
+}
+
+/// Directory of the blobs stored outside the timeline file. It is next to
+/// the timeline file. None if the timeline is not in a file.
+pub fn objects_dir(conn: &Connection) -> Option<PathBuf> {
+    let file = conn.query_row(
+        "select file from pragma_database_list where name = 'main';",
+        params![],
+        |row| {
+            let file: String = row.get(0)?;
+            Ok(file)
+        }
+    );
+    let file = file.ok()?;
+    if file.len() == 0 {
+        return None;
+    }
+    let dir = Path::new(&file).parent()?;
+    return Some(dir.join("objects"));
+}
+
+/// File of a blob in the objects directory, like objects/ab/cdef... An
+/// encoded blob has the codec as its extension, like objects/ab/cdef....deflate
+/// and a hash of another algorithm than SHA-256 is in a directory of its own,
+/// like objects/blake3/ab/cdef...
+pub fn object_path(objects: &Path, content_sha256: &str, codec: Codec) -> PathBuf {
+    let (objects, hex) = match hash::split_algorithm(content_sha256) {
+        Ok((hash::Algorithm::Sha256, hex)) => (objects.to_path_buf(), hex),
+        Ok((algorithm, hex)) => (objects.join(algorithm.name()), hex),
+        Err(_) => (objects.to_path_buf(), content_sha256),
+    };
+    let (dir, file) = hex.split_at(2.min(hex.len()));
+    let path = objects.join(dir).join(file);
+    match codec.name() {
+        Some(extension) => path.with_extension(extension),
+        None => path,
+    }
+}
+
+/// Copy the encoded content into the objects directory and store only a
+/// pointer row with a null blob in the timeline.
+fn write_new_object(conn: &Connection, source: &Source, hash: &hash::Hash, codec: Codec, objects: &Path) -> Result<i64, &'static str> {
+    let input = source();
+    if input.is_err() {
+        return Err("Fail to read the file");
+    }
+    let mut input = input.unwrap();
+
+    let object = object_path(objects, &hash.to_string(), codec);
+    let dir = object.parent().unwrap();
+    if fs::create_dir_all(dir).is_err() {
+        return Err("Fail to create the objects directory");
+    }
+
+    // Write to a temporary file first so that a reader never sees a half
+    // written object under its final name.
+    let temporary = object.with_extension("tmp");
+    let output = fs::File::create(&temporary);
+    if output.is_err() {
+        return Err("Fail to create the object file");
+    }
+    let mut output = output.unwrap();
+    let success = copy_verified(&mut input, codec, None, &mut output, hash)
+        .and_then(|size| output.sync_all().map(|_| size).map_err(|_| "Fail to write the object file"))
+        .and_then(|size| fs::rename(&temporary, &object).map(|_| size).map_err(|_| "Fail to write the object file"));
+    if let Err(msg) = success {
+        let _ = fs::remove_file(&temporary);
+        return Err(msg);
+    }
+    let size = success.unwrap();
+
+    let success = conn.execute(
+        "insert into content(content_sha256, blob, stored_time, object_size, codec) values (?1, null, ?2, ?3, ?4);",
+        params![hash.to_string(), Timestamp::now().to_string(), size as i64, codec.name()],
+    );
+    if let Err(msg) = success {
+        eprintln!("Fail to save blob content to timeline file. Reason: {}", msg);
+        return Err("Fail to save blob content");
+    }
+    return Ok(conn.last_insert_rowid());
+}
+
+/// Encode everything from the input to the output and check that the
+/// decoded content has the expected hash. The encoded content must fit in
+/// the size if one is given. Return the size of the encoded content.
+fn copy_verified(input: &mut dyn Read, codec: Codec, size: Option<u64>, output: &mut dyn Write, hash: &hash::Hash) -> Result<u64, &'static str> {
+    // Hash the content again while copying it. The file may have changed
+    // after it was hashed the first time.
+    let mut hasher = hash::Hasher::new(hash.algorithm());
+    let mut counter = Counter::new(output, size);
+    let mut encoder = codec.encoder(&mut counter);
+    let mut buffer = vec![0u8; hash::CHUNK_SIZE];
+    loop {
+        let count = input.read(&mut buffer);
+        if count.is_err() {
+            return Err("Fail to read the file");
+        }
+        let count = count.unwrap();
+        if count == 0 {
+            break;
+        }
+        if encoder.write_all(&buffer[..count]).is_err() {
+            return Err("The file changed while it was saved");
+        }
+        hasher.update(&buffer[..count]);
+    }
+    if encoder.finish().is_err() {
+        return Err("The file changed while it was saved");
+    }
+
+    if hasher.finish() != *hash {
+        return Err("The file changed while it was saved");
+    }
+    return Ok(counter.count);
+}
+
+/// Row id of a stored blob. Needed to open the blob for incremental I/O.
+/// A hash that the content had before it was rehashed finds it too.
+fn blob_rowid(conn: &Connection, sha256: &hash::Hash) -> Option<i64> {
+    let row = conn.query_row(
+        "select rowid from content where content_sha256 = ?1 limit 1;",
+        params![sha256.to_string()],
+        |row| {
+            let rowid: i64 = row.get(0)?;
+            Ok(rowid)
+        }
+    );
+    if row.is_ok() || !table_exists(conn, "hash_alias") {
+        return row.ok();
+    }
+
+    let row = conn.query_row(
+        "select content.rowid from hash_alias join content on content_sha256 = new_id where old_id = ?1 limit 1;",
+        params![sha256.to_string()],
+        |row| {
+            let rowid: i64 = row.get(0)?;
+            Ok(rowid)
+        }
+    );
+    return row.ok();
+}
+
+pub fn exists(conn: &Connection, sha256: &hash::Hash) -> bool {
+    return blob_rowid(conn, sha256).is_some();
+}
+
+/// Copy a stored blob into the writer one chunk at a time. Return the size
+/// of the blob or None if the blob is not stored.
+pub fn load_to(conn: &Connection, sha256: &hash::Hash, writer: &mut dyn Write) -> Result<Option<u64>, String> {
+    let rowid = blob_rowid(conn, sha256);
+    if rowid.is_none() {
+        return Ok(None);
+    }
+    let size = copy_row_to(conn, rowid.unwrap(), writer)?;
+    return Ok(Some(size));
+}
 
-            if let Err(msg) = success {
-                panic!("Fail to save blob content to timeline file. Reason: {}", msg);
-            }
+/// Copy a stored blob into the file. The blob is written next to the file
+/// first and renamed over it only when all of it was read, so a blob that
+/// cannot be read leaves the old file as it was. Return the size of the blob
+/// or None if the blob is not stored.
+pub fn load_to_file(conn: &Connection, sha256: &hash::Hash, file: &Path) -> Result<Option<u64>, String> {
+    let name = file.file_name()
+        .ok_or_else(|| format!("Not a file name: {}", file.to_string_lossy()))?;
+    let temporary = file.with_file_name(format!("~{}", name.to_string_lossy()));
+
+    let mut output = fs::File::create(&temporary)
+        .map_err(|msg| format!("Fail to write {}. Reason: {}", temporary.to_string_lossy(), msg))?;
+    let size = load_to(conn, sha256, &mut output).and_then(|size| {
+        output.sync_all()
+            .map_err(|msg| format!("Fail to write {}. Reason: {}", temporary.to_string_lossy(), msg))?;
+        return Ok(size);
+    });
+    drop(output);
+
+    if let Ok(Some(_)) = size {
+        if let Err(msg) = fs::rename(&temporary, file) {
+            let _ = fs::remove_file(&temporary);
+            return Err(format!("Fail to write {}. Reason: {}", file.to_string_lossy(), msg));
         }
     } else {
-        panic!("Content table missing");
+        let _ = fs::remove_file(&temporary);
     }
+    return size;
+}
+
+/// Open the decoded content of a row for reading, whether it is in the
+/// timeline file or in the objects directory.
+pub fn open_row(conn: &Connection, rowid: i64) -> Result<Box<dyn Read + '_>, String> {
+    let row = row_by_id(conn, rowid)?;
+    let input: Box<dyn Read> = if row.object {
+        let objects = objects_dir(conn)
+            .ok_or_else(|| String::from("The blob is in the objects directory but the timeline is not in a file"))?;
+        let object = object_path(&objects, &row.content_sha256, row.codec);
+        let file = fs::File::open(&object)
+            .map_err(|msg| format!("Fail to open the object file {}. Reason: {}", object.display(), msg))?;
+        Box::new(file)
+    } else {
+        let blob = conn.blob_open(DatabaseName::Main, "content", "blob", rowid, true)
+            .map_err(|msg| format!("Fail to open the blob. Reason: {}", msg))?;
+        Box::new(blob)
+    };
+    return Ok(row.codec.decoder(input));
+}
 
-    return hash;
+fn copy_row_to(conn: &Connection, rowid: i64, writer: &mut dyn Write) -> Result<u64, String> {
+    let mut input = open_row(conn, rowid)?;
+    return copy_chunks(&mut input, writer);
 }
 
+fn copy_chunks(input: &mut dyn Read, writer: &mut dyn Write) -> Result<u64, String> {
+    let mut buffer = vec![0u8; hash::CHUNK_SIZE];
+    let mut size: u64 = 0;
+    loop {
+        let count = input.read(&mut buffer)
+            .map_err(|msg| format!("Fail to read the blob. Reason: {}", msg))?;
+        if count == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..count])
+            .map_err(|msg| format!("Fail to write the blob. Reason: {}", msg))?;
+        size += count as u64;
+    }
+
+    return Ok(size);
+}
+
+/// Load a whole blob into memory. Prefer load_to(..) for anything that may be large.
 pub fn load(conn: &Connection, sha256: hash::Hash) -> Option<Vec<u8>> {
-    let blob = conn.query_row(
-        "select blob from content where content_sha256 = ?1",
-        params![sha256.to_string()],
+    let mut content = Vec::new();
+    match load_to(conn, &sha256, &mut content) {
+        Ok(Some(_)) => Some(content),
+        _ => None,
+    }
+}
+
+/// A row of the content table. There should be one row per hash.
+pub struct StoredRow {
+    pub rowid: i64,
+    pub content_sha256: String,
+    /// The blob is in the objects directory instead of the timeline file.
+    pub object: bool,
+    pub codec: Codec,
+    /// Size of the encoded content.
+    pub stored_size: u64,
+}
+
+const ROW_COLUMNS: &str = "rowid, content_sha256, object_size is not null, codec, coalesce(object_size, length(blob), 0)";
+
+fn stored_row(row: &rusqlite::Row) -> Result<StoredRow, rusqlite::Error> {
+    let size: i64 = row.get(4)?;
+    Ok(StoredRow {
+        rowid: row.get(0)?,
+        content_sha256: row.get(1)?,
+        object: row.get(2)?,
+        codec: row.get(3)?,
+        stored_size: size as u64,
+    })
+}
+
+pub fn row_by_id(conn: &Connection, rowid: i64) -> Result<StoredRow, String> {
+    let sql = format!("select {} from content where rowid = ?1;", ROW_COLUMNS);
+    let row = conn.query_row(&sql, params![rowid], stored_row)
+        .map_err(|msg| format!("Fail to read the content row {}. Reason: {}", rowid, msg))?;
+    return Ok(row);
+}
+
+pub fn all_rows(conn: &Connection) -> Result<Vec<StoredRow>, &'static str> {
+    let stmt = conn.prepare(&format!("select {} from content order by content_sha256, rowid;", ROW_COLUMNS));
+    if stmt.is_err() {
+        return Err("Content table missing");
+    }
+    let mut stmt = stmt.unwrap();
+
+    let rows = stmt.query_map(params![], stored_row);
+    if rows.is_err() {
+        return Err("Fail to read the content table");
+    }
+
+    let mut result = Vec::new();
+    for row in rows.unwrap() {
+        if let Ok(row) = row {
+            result.push(row);
+        } else {
+            return Err("Fail to read the content table");
+        }
+    }
+    return Ok(result);
+}
+
+/// Calculate the hash of the stored content again with the algorithm of the
+/// hash it is stored with.
+pub fn rehash_row(conn: &Connection, rowid: i64) -> Result<hash::Hash, String> {
+    let row = row_by_id(conn, rowid)?;
+    let (algorithm, _) = hash::split_algorithm(&row.content_sha256)?;
+    return rehash_row_with(conn, rowid, algorithm);
+}
+
+pub fn rehash_row_with(conn: &Connection, rowid: i64, algorithm: hash::Algorithm) -> Result<hash::Hash, String> {
+    let mut hasher = hash::Hasher::new(algorithm);
+    copy_row_to(conn, rowid, &mut hasher)?;
+    return Ok(hasher.finish());
+}
+
+/// Replace a row with a new copy of the same content. The copy keeps the
+/// stored time of the original.
+pub fn replace_row(conn: &Connection, old_rowid: i64, new_rowid: i64) -> Result<(), &'static str> {
+    let success = conn.execute(
+        "update content set stored_time = (select stored_time from content where rowid = ?1) where rowid = ?2;",
+        params![old_rowid, new_rowid]
+    );
+    if success.is_err() {
+        return Err("Fail to update the blob");
+    }
+    return delete_row(conn, old_rowid);
+}
+
+pub fn delete_row(conn: &Connection, rowid: i64) -> Result<(), &'static str> {
+    let success = conn.execute("delete from content where rowid = ?1;", params![rowid]);
+    match success {
+        Ok(_) => Ok(()),
+        Err(_) => Err("Fail to delete the blob"),
+    }
+}
+
+pub struct BlobInfo {
+    pub content_sha256: String,
+    pub size: usize,
+}
+
+/// All stored blobs ordered by their hash.
+pub fn list(conn: &Connection) -> Result<Vec<BlobInfo>, &'static str> {
+    let stmt = conn.prepare("select content_sha256, coalesce(object_size, length(blob)) from content order by content_sha256;");
+    if stmt.is_err() {
+        return Err("Content table missing");
+    }
+    let mut stmt = stmt.unwrap();
+
+    let rows = stmt.query_map(params![], |row| {
+        let content_sha256: String = row.get(0)?;
+        let size: Option<i64> = row.get(1)?;
+        Ok(BlobInfo { content_sha256, size: size.unwrap_or(0) as usize })
+    });
+    if rows.is_err() {
+        return Err("Fail to read the content table");
+    }
+
+    let mut blobs = Vec::new();
+    for row in rows.unwrap() {
+        if let Ok(info) = row {
+            blobs.push(info);
+        } else {
+            return Err("Fail to read the content table");
+        }
+    }
+    return Ok(blobs);
+}
+
+/// Shortest abbreviation a hash may have.
+pub const MIN_PREFIX_LEN: usize = 4;
+
+/// Find the stored blob whose hash starts with the given hex digits, like the
+/// short hashes of git. The prefix must be unambiguous.
+///
+/// The prefix may name the algorithm, like blake3:af13. Without one it
+/// matches the hashes of every algorithm. The hashes the content had before
+/// it was rehashed match too.
+pub fn resolve_prefix(conn: &Connection, prefix: &str) -> Result<hash::Hash, String> {
+    let text = prefix.to_ascii_lowercase();
+    let (algorithm, prefix) = match text.find(':') {
+        Some(_) => {
+            let (algorithm, hex) = hash::split_algorithm(&text)?;
+            (Some(algorithm), hex)
+        },
+        None => (None, &text[..]),
+    };
+    if prefix.len() < MIN_PREFIX_LEN {
+        return Err(format!("A hash prefix must have at least {} hex digits", MIN_PREFIX_LEN));
+    }
+    if !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Not a hash: {}", text));
+    }
+
+    // Pairs of the matching hash and the hash the content is stored with.
+    let mut sql = format!(
+        "select content_sha256, content_sha256 from content where substr({}, 1, ?2) = ?1",
+        hex_part("content_sha256")
+    );
+    if table_exists(conn, "hash_alias") {
+        sql.push_str(&format!(
+            " union select old_id, new_id from hash_alias where substr({}, 1, ?2) = ?1",
+            hex_part("old_id")
+        ));
+    }
+    sql.push_str(" order by 2;");
+
+    let mut stmt = conn.prepare(&sql).map_err(|_| String::from("Content table missing"))?;
+    let rows = stmt.query_map(params![prefix, prefix.len() as u32], |row| {
+        let matched: String = row.get(0)?;
+        let content_sha256: String = row.get(1)?;
+        Ok((matched, content_sha256))
+    }).map_err(|_| String::from("Fail to read the content table"))?;
+
+    let mut candidates: Vec<String> = Vec::new();
+    for row in rows {
+        let (matched, content_sha256) = row.map_err(|_| String::from("Fail to read the content table"))?;
+        if algorithm.is_some() && hash::split_algorithm(&matched).ok().map(|(algorithm, _)| algorithm) != algorithm {
+            continue;
+        }
+        if !candidates.contains(&content_sha256) {
+            candidates.push(content_sha256);
+        }
+    }
+    let prefix = text;
+
+    match candidates.len() {
+        0 => Err(format!("No blob matches {}", prefix)),
+        1 => hash::Hash::from_text(&candidates[0]),
+        _ => Err(format!("The hash prefix {} is ambiguous. Candidates:\n  {}", prefix, candidates.join("\n  "))),
+    }
+}
+
+/// SQL expression of the hex digits of a hash column without the algorithm.
+fn hex_part(column: &str) -> String {
+    return format!("substr({}, instr({}, ':') + 1)", column, column);
+}
+
+/// Length of the shortest unique prefix of each hash in a sorted list of
+/// distinct hashes, but at least MIN_PREFIX_LEN.
+pub fn unique_prefix_lengths(sorted_hashes: &[String]) -> Vec<usize> {
+    let common = |a: &str, b: &str| a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count();
+
+    let mut lengths = Vec::new();
+    for (i, hash) in sorted_hashes.iter().enumerate() {
+        let mut len = MIN_PREFIX_LEN;
+        if i > 0 {
+            len = len.max(common(hash, &sorted_hashes[i - 1]) + 1);
+        }
+        if i + 1 < sorted_hashes.len() {
+            len = len.max(common(hash, &sorted_hashes[i + 1]) + 1);
+        }
+        lengths.push(len.min(hash.len()));
+    }
+    return lengths;
+}
+
+
+/// Columns that refer to blobs by their hash. A blob referred by none of
+/// them is garbage. Tables missing from an older timeline are skipped.
+pub const REFERENCES: [(&str, &str); 3] = [
+    ("card", "content_sha256"),
+    ("card_revision", "content_sha256"),
+    ("attachment", "content_sha256"),
+];
+
+pub fn table_exists(conn: &Connection, table: &str) -> bool {
+    let count = conn.query_row(
+        "select count(*) from sqlite_master where type = 'table' and name = ?1;",
+        params![table],
         |row| {
-            let blob: Vec<u8> = row.get(0)?;
-            Ok(blob)
+            let count: u32 = row.get(0)?;
+            Ok(count)
         }
     );
+    return count.unwrap_or(0) > 0;
+}
 
-    if let Err(_) = blob {
-        return None;
+/// Blobs that no card, revision or other table refers to. With a time given,
+/// only blobs stored before it.
+pub fn unreferenced(conn: &Connection, stored_before: Option<&Timestamp>) -> Result<Vec<BlobInfo>, &'static str> {
+    let referenced: Vec<String> = REFERENCES.iter()
+        .filter(|(table, _)| table_exists(conn, table))
+        .map(|(table, column)| format!("select {} from {} where {} is not null", column, table, column))
+        .collect();
+
+    let mut sql = String::from("select content_sha256, coalesce(object_size, length(blob)) from content where 1 = 1");
+    if referenced.len() > 0 {
+        sql.push_str(&format!(" and content_sha256 not in ({})", referenced.join(" union ")));
+    }
+    if stored_before.is_some() {
+        sql.push_str(" and (stored_time is null or stored_time < ?1)");
+    }
+    sql.push_str(" order by content_sha256;");
+
+    let stmt = conn.prepare(&sql);
+    if stmt.is_err() {
+        return Err("Fail to prepare a query");
+    }
+    let mut stmt = stmt.unwrap();
+
+    let map_row = |row: &rusqlite::Row| {
+        let content_sha256: String = row.get(0)?;
+        let size: Option<i64> = row.get(1)?;
+        Ok(BlobInfo { content_sha256, size: size.unwrap_or(0) as usize })
+    };
+    let rows = match stored_before {
+        Some(time) => stmt.query_map(params![time.to_string()], map_row),
+        None => stmt.query_map(params![], map_row),
+    };
+    if rows.is_err() {
+        return Err("Fail to read the content table");
+    }
+
+    let mut blobs = Vec::new();
+    for row in rows.unwrap() {
+        if let Ok(info) = row {
+            blobs.push(info);
+        } else {
+            return Err("Fail to read the content table");
+        }
+    }
+    return Ok(blobs);
+}
+
+/// Remove the file of a blob from the objects directory. Call this only
+/// after the rows of the blob are deleted for good.
+pub fn remove_object(conn: &Connection, content_sha256: &str) -> Result<(), &'static str> {
+    let objects = objects_dir(conn);
+    if objects.is_none() {
+        return Ok(());
+    }
+    let objects = objects.unwrap();
+    for codec in [Codec::Identity, Codec::Deflate].iter() {
+        remove_object_file(&object_path(&objects, content_sha256, *codec))?;
     }
+    Ok(())
+}
+
+pub fn remove_object_file(object: &Path) -> Result<(), &'static str> {
+    if object.is_file() && fs::remove_file(object).is_err() {
+        return Err("Fail to remove the object file");
+    }
+    // The ab/ directory stays if another blob shares it.
+    if let Some(dir) = object.parent() {
+        let _ = fs::remove_dir(dir);
+    }
+    Ok(())
+}
+
+/// Delete every stored copy of the blob.
+pub fn delete(conn: &Connection, content_sha256: &str) -> Result<(), &'static str> {
+    let success = conn.execute("delete from content where content_sha256 = ?1;", params![content_sha256]);
+    match success {
+        Ok(_) => Ok(()),
+        Err(_) => Err("Fail to delete the blob"),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::carddb;
+    use crate::model::schema::TestTimeline;
+
+    #[test]
+    fn test_unique_prefix_lengths() {
+        let hashes: Vec<String> = ["0123aa", "0123ab", "4567", "45ff00", "9"]
+            .iter().map(|s| s.to_string()).collect();
+        assert_eq!(unique_prefix_lengths(&hashes), vec![6, 6, 4, 4, 1]);
+        assert_eq!(unique_prefix_lengths(&[]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_resolve_prefix() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("
+            create table content(content_sha256 text not null, blob blob);
+            insert into content values ('aaaa1111aaaa1111aaaa1111aaaa1111aaaa1111aaaa1111aaaa1111aaaa1111', x'00');
+            insert into content values ('aaaa2222aaaa2222aaaa2222aaaa2222aaaa2222aaaa2222aaaa2222aaaa2222', x'00');
+        ").unwrap();
 
-    return Some(blob.unwrap());
+        let hash = resolve_prefix(&conn, "AAAA1").unwrap();
+        assert_eq!(hash.to_string(), "aaaa1111aaaa1111aaaa1111aaaa1111aaaa1111aaaa1111aaaa1111aaaa1111");
+        assert!(resolve_prefix(&conn, "aaa").is_err());
+        assert!(resolve_prefix(&conn, "aaaa").unwrap_err().contains("ambiguous"));
+        assert!(resolve_prefix(&conn, "bbbb").is_err());
+        assert!(resolve_prefix(&conn, "aa%_").is_err());
+    }
+
+    #[test]
+    fn test_sidecar_round_trip() {
+        let timeline = TestTimeline::new("sidecar");
+        let (dir, conn) = (&timeline.dir, &timeline.conn);
+        carddb::set_sidecar_threshold(conn, Some(4)).unwrap();
+        carddb::set_codec(conn, Codec::Deflate).unwrap();
+
+        let small = dir.join("small");
+        let large = dir.join("large");
+        fs::write(&small, b"abc").unwrap();
+        fs::write(&large, b"abcdefgh").unwrap();
+        let small = save(conn, &small).unwrap();
+        let large = save(conn, &large).unwrap();
+
+        let object = object_path(&dir.join("objects"), &large.to_string(), Codec::Identity);
+        assert!(object.is_file());
+        assert!(!object_path(&dir.join("objects"), &small.to_string(), Codec::Identity).exists());
+        assert_eq!(load(conn, small.clone()), Some(b"abc".to_vec()));
+        assert_eq!(load(conn, large.clone()), Some(b"abcdefgh".to_vec()));
+
+        let sizes: Vec<usize> = list(conn).unwrap().iter().map(|info| info.size).collect();
+        assert!(sizes.contains(&3) && sizes.contains(&8));
+
+        let rowid = blob_rowid(conn, &large).unwrap();
+        fs::write(&object, b"changed").unwrap();
+        assert_ne!(rehash_row(conn, rowid).unwrap(), large);
+        fs::remove_file(&object).unwrap();
+        assert!(rehash_row(conn, rowid).is_err());
+
+        // A missing object leaves the file as it was.
+        let card = dir.join("card");
+        fs::write(&card, b"my text").unwrap();
+        assert!(load_to_file(conn, &large, &card).is_err());
+        assert_eq!(fs::read(&card).unwrap(), b"my text".to_vec());
+        assert!(!dir.join("~card").exists());
+        assert_eq!(load_to_file(conn, &small, &card), Ok(Some(3)));
+        assert_eq!(fs::read(&card).unwrap(), b"abc".to_vec());
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let timeline = TestTimeline::new("codec");
+        let (dir, conn) = (&timeline.dir, &timeline.conn);
+        carddb::set_sidecar_threshold(conn, Some(1000)).unwrap();
+        carddb::set_codec(conn, Codec::Deflate).unwrap();
+
+        let text = "A card about cards. ".repeat(20);
+        let large = "A card about cards. ".repeat(200);
+        fs::write(dir.join("text"), &text).unwrap();
+        fs::write(dir.join("large"), &large).unwrap();
+        let text_hash = save(conn, &dir.join("text")).unwrap();
+        let large_hash = save(conn, &dir.join("large")).unwrap();
+        assert_eq!(text_hash, hash::Hash::file(&dir.join("text"), hash::Algorithm::Sha256).unwrap());
+
+        let rows = all_rows(conn).unwrap();
+        assert!(rows.iter().all(|row| row.codec == Codec::Deflate));
+        assert!(rows.iter().all(|row| row.stored_size < text.len() as u64));
+        assert!(object_path(&dir.join("objects"), &large_hash.to_string(), Codec::Deflate).is_file());
+        assert_eq!(load(conn, text_hash), Some(text.into_bytes()));
+        assert_eq!(load(conn, large_hash), Some(large.into_bytes()));
+    }
 }
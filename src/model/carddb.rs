@@ -1,33 +1,135 @@
-use crate::card::Face;
+use crate::card::{Face, Timestamp};
+use crate::card::config::CardConfig;
+use crate::card::layout::FolderLayout;
+use crate::card::naming::NamingScheme;
+use crate::card::pattern::FilePattern;
 use crate::hash;
+use crate::model::blob;
+use crate::model::codec::Codec;
 use rusqlite::{Connection, params};
+use std::collections::HashSet;
 use std::path::{PathBuf, Path};
 
-fn cards(conn: &mut Connection) -> Vec<Face> {
-    let mut stmt = conn.prepare("select card_name from card;").unwrap();
-    let cards = stmt.query_map(
-        params![],
-        |row| {
-            let name: String = row.get(0)?;
-            let card = Face::from_name(name.as_str()).unwrap();
-            Ok(card)
-        });
-    let cards: Vec<Face> = cards
-        .unwrap()
-        .map(|maybe_card| maybe_card.unwrap())
-        .collect();
-    return cards;
+/// Tables whose card_name column names a card.
+pub const CARD_NAME_TABLES: [&str; 4] = ["card", "card_revision", "attachment", "card_reservation"];
+
+/// Every card name in the timeline: the cards, the history of the deleted
+/// cards, the cards of attachments and the reserved names.
+pub fn timeline_card_names(conn: &Connection) -> Result<HashSet<String>, String> {
+    let mut names = HashSet::new();
+    for table in CARD_NAME_TABLES.iter() {
+        if !blob::table_exists(conn, table) {
+            continue;
+        }
+        let sql = format!("select distinct card_name from {};", table);
+        let mut stmt = conn.prepare(&sql).map_err(|_| String::from("Fail to prepare a query"))?;
+        let rows = stmt.query_map(params![], |row| row.get(0))
+            .map_err(|_| format!("Fail to read the {} table", table))?;
+        for row in rows {
+            names.insert(row.map_err(|_| format!("Fail to read the {} table", table))?);
+        }
+    }
+    return Ok(names);
 }
 
+/// Reserve the name for a new card. False if the name is already reserved.
+pub fn reserve_card(conn: &Connection, card: &Face) -> Result<bool, &'static str> {
+    // Note(wistrandj): A timeline without the card_reservation feature has
+    // no such table. Nothing is reserved until `zk init` adds it.
+    if !blob::table_exists(conn, "card_reservation") {
+        return Ok(true);
+    }
+    let success = conn.execute(
+        "insert or ignore into card_reservation(card_name, reserved_time) values (?1, ?2);",
+        params![card.name(), Timestamp::now().to_string()]
+    );
 
-pub fn save_card_and_hash(conn: &Connection, card: &Face, hash: &hash::Hash) {
-    let mut stmt = conn.prepare("insert or replace into card(card_name, content_sha256) values (?1, ?2);").unwrap();
+    match success {
+        Ok(count) => Ok(count == 1),
+        Err(msg) => {
+            eprintln!("Fail to reserve the card. Reason: {}", msg);
+            Err("Fail to reserve the card")
+        }
+    }
+}
+
+pub fn release_card(conn: &Connection, card: &Face) -> Result<(), &'static str> {
+    if !blob::table_exists(conn, "card_reservation") {
+        return Ok(());
+    }
+    let success = conn.execute("delete from card_reservation where card_name = ?1;", params![card.name()]);
+    if let Err(msg) = success {
+        eprintln!("Fail to release the card. Reason: {}", msg);
+        return Err("Fail to release the card");
+    }
+    Ok(())
+}
+
+/// Release the names reserved before the given time. Return the number of
+/// released names.
+pub fn release_stale_reservations(conn: &Connection, reserved_before: &Timestamp) -> Result<usize, &'static str> {
+    if !blob::table_exists(conn, "card_reservation") {
+        return Ok(0);
+    }
+    // Note(wistrandj): The times are RFC 3339 in UTC with whole seconds so
+    // they sort as text.
+    let success = conn.execute(
+        "delete from card_reservation where reserved_time < ?1;",
+        params![reserved_before.to_string()]
+    );
+    match success {
+        Ok(count) => Ok(count),
+        Err(msg) => {
+            eprintln!("Fail to release the reserved cards. Reason: {}", msg);
+            Err("Fail to release the reserved cards")
+        }
+    }
+}
+
+/// Save a new card. Refuse to overwrite a card that is in the timeline.
+pub fn create_card_and_hash(conn: &Connection, card: &Face, hash: &hash::Hash, commit_id: usize) -> Result<(), &'static str> {
+    if card_hash(conn, card).is_some() {
+        return Err("The card is already in the timeline");
+    }
+    return save_card_and_hash(conn, card, hash, commit_id);
+}
+
+pub fn save_card_and_hash(conn: &Connection, card: &Face, hash: &hash::Hash, commit_id: usize) -> Result<(), &'static str> {
+    // The card keeps its create time over saves. Both times come from the commit.
+    let mut stmt = conn.prepare(
+        "insert or replace into card(card_name, content_sha256, commit_id, create_time, modify_time)
+            select ?1, ?2, commit_id, coalesce((select create_time from card where card_name = ?1), commit_time), commit_time
+            from commit_log where commit_id = ?3;").unwrap();
     let name: String = card.name();
     let hash: String = hash.to_string();
-    let success = stmt.execute(params![name, hash]);
-    if let Err(msg) = success {
-        eprintln!("Fail to save card and the hash");
+    // Note(wistrandj): Both inserts select from the commit. An unknown
+    // commit inserts nothing without an error.
+    match stmt.execute(params![name, hash, commit_id as u32]) {
+        Ok(0) => return Err("No such commit to save the card in"),
+        Ok(_) => {},
+        Err(msg) => {
+            eprintln!("Fail to save card and the hash. Reason: {}", msg);
+            return Err("Fail to save card and the hash");
+        }
+    }
+
+    let success = conn.execute(
+        "insert into card_revision(card_name, content_sha256, commit_id, revision_time, author, commit_message)
+            select ?1, ?2, commit_id, commit_time, author, message from commit_log where commit_id = ?3;",
+        params![name, hash, commit_id as u32]
+    );
+    match success {
+        Ok(0) => return Err("No such commit to save the card revision in"),
+        Ok(_) => {},
+        Err(msg) => {
+            eprintln!("Fail to save the card revision. Reason: {}", msg);
+            return Err("Fail to save the card revision");
+        }
     }
+
+    // The reservation is not needed once the card is saved.
+    release_card(conn, card)?;
+    Ok(())
 }
 
 pub fn default_location(conn: &Connection) -> Option<PathBuf> {
@@ -60,6 +162,200 @@ pub fn set_default_location(conn: &Connection, location: &Path) {
     }
 }
 
+/// Size in bytes above which a blob is stored in the objects directory
+/// instead of the timeline file. None if every blob goes to the timeline.
+pub fn sidecar_threshold(conn: &Connection) -> Option<u64> {
+    let row = conn.query_row(
+        "select sidecar_threshold from configuration;",
+        params![],
+        |row| {
+            let threshold: Option<i64> = row.get(0)?;
+            Ok(threshold)
+        }
+    );
+
+    // Note(wistrandj): A timeline without the sidecar feature has no such column.
+    return match row {
+        Ok(Some(threshold)) if threshold >= 0 => Some(threshold as u64),
+        _ => None,
+    }
+}
+
+pub fn set_sidecar_threshold(conn: &Connection, threshold: Option<u64>) -> Result<(), &'static str> {
+    let success = conn.execute(
+        "update configuration set sidecar_threshold = ?1;",
+        params![threshold.map(|threshold| threshold as i64)]
+    );
+
+    if let Err(msg) = success {
+        eprintln!("Fail to set the sidecar threshold. Reason: {}", msg);
+        return Err("Fail to set the sidecar threshold");
+    }
+    Ok(())
+}
+
+/// Codec of the new blobs. Identity if the timeline does not compress blobs.
+pub fn codec(conn: &Connection) -> Codec {
+    let row = conn.query_row(
+        "select codec from configuration;",
+        params![],
+        |row| {
+            let codec: Codec = row.get(0)?;
+            Ok(codec)
+        }
+    );
+
+    // Note(wistrandj): A timeline without the codec feature has no such column.
+    return row.unwrap_or(Codec::Identity);
+}
+
+pub fn set_codec(conn: &Connection, codec: Codec) -> Result<(), &'static str> {
+    let success = conn.execute(
+        "update configuration set codec = ?1;",
+        params![codec.name()]
+    );
+
+    if let Err(msg) = success {
+        eprintln!("Fail to set the codec. Reason: {}", msg);
+        return Err("Fail to set the codec");
+    }
+    Ok(())
+}
+
+/// Algorithm to hash new content with. SHA-256 unless the timeline is
+/// rehashed with another one.
+pub fn hash_algorithm(conn: &Connection) -> hash::Algorithm {
+    let row = conn.query_row(
+        "select hash_algorithm from configuration;",
+        params![],
+        |row| {
+            let name: Option<String> = row.get(0)?;
+            Ok(name)
+        }
+    );
+
+    // Note(wistrandj): A timeline without the hash_algorithm feature has no such column.
+    return match row {
+        Ok(Some(name)) => hash::Algorithm::from_name(&name).unwrap_or(hash::Algorithm::Sha256),
+        _ => hash::Algorithm::Sha256,
+    }
+}
+
+pub fn set_hash_algorithm(conn: &Connection, algorithm: hash::Algorithm) -> Result<(), &'static str> {
+    let success = conn.execute(
+        "update configuration set hash_algorithm = ?1;",
+        params![algorithm.name()]
+    );
+
+    if let Err(msg) = success {
+        eprintln!("Fail to set the hash algorithm. Reason: {}", msg);
+        return Err("Fail to set the hash algorithm");
+    }
+    Ok(())
+}
+
+/// How the cards of the timeline are named. Alphanumeric unless set otherwise.
+pub fn naming_scheme(conn: &Connection) -> NamingScheme {
+    let row = conn.query_row(
+        "select naming_scheme from configuration;",
+        params![],
+        |row| {
+            let name: Option<String> = row.get(0)?;
+            Ok(name)
+        }
+    );
+
+    // Note(wistrandj): A timeline without the naming_scheme feature has no such column.
+    return match row {
+        Ok(name) => NamingScheme::from_name(name.as_deref()).unwrap_or(NamingScheme::Alphanumeric),
+        _ => NamingScheme::Alphanumeric,
+    }
+}
+
+pub fn set_naming_scheme(conn: &Connection, scheme: NamingScheme) -> Result<(), &'static str> {
+    let success = conn.execute(
+        "update configuration set naming_scheme = ?1;",
+        params![scheme.name()]
+    );
+
+    if let Err(msg) = success {
+        eprintln!("Fail to set the naming scheme. Reason: {}", msg);
+        return Err("Fail to set the naming scheme");
+    }
+    Ok(())
+}
+
+/// How the card files are named. As the cards unless set otherwise.
+pub fn file_pattern(conn: &Connection) -> FilePattern {
+    let row = conn.query_row(
+        "select file_pattern from configuration;",
+        params![],
+        |row| {
+            let name: Option<String> = row.get(0)?;
+            Ok(name)
+        }
+    );
+
+    // Note(wistrandj): A timeline without the file_pattern feature has no such column.
+    return match row {
+        Ok(name) => FilePattern::from_name(name.as_deref()).unwrap_or_default(),
+        _ => FilePattern::default(),
+    }
+}
+
+pub fn set_file_pattern(conn: &Connection, pattern: &FilePattern) -> Result<(), &'static str> {
+    let success = conn.execute(
+        "update configuration set file_pattern = ?1;",
+        params![pattern.name()]
+    );
+
+    if let Err(msg) = success {
+        eprintln!("Fail to set the file pattern. Reason: {}", msg);
+        return Err("Fail to set the file pattern");
+    }
+    Ok(())
+}
+
+/// Where the card files are in the card folder. Flat unless set otherwise.
+pub fn folder_layout(conn: &Connection) -> FolderLayout {
+    let row = conn.query_row(
+        "select folder_layout from configuration;",
+        params![],
+        |row| {
+            let name: Option<String> = row.get(0)?;
+            Ok(name)
+        }
+    );
+
+    // Note(wistrandj): A timeline without the folder_layout feature has no such column.
+    return match row {
+        Ok(name) => FolderLayout::from_name(name.as_deref()).unwrap_or(FolderLayout::Flat),
+        _ => FolderLayout::Flat,
+    }
+}
+
+pub fn set_folder_layout(conn: &Connection, layout: FolderLayout) -> Result<(), &'static str> {
+    let success = conn.execute(
+        "update configuration set folder_layout = ?1;",
+        params![layout.name()]
+    );
+
+    if let Err(msg) = success {
+        eprintln!("Fail to set the folder layout. Reason: {}", msg);
+        return Err("Fail to set the folder layout");
+    }
+    Ok(())
+}
+
+/// The naming scheme, the file pattern and the folder layout of the timeline.
+pub fn card_config(conn: &Connection) -> CardConfig {
+    return CardConfig {
+        scheme: naming_scheme(conn),
+        pattern: file_pattern(conn),
+        layout: folder_layout(conn),
+    };
+}
+
 pub fn version(conn: &Connection) -> Option<usize> {
     let row = conn.query_row(
         "select version from configuration;",
@@ -80,3 +376,63 @@ pub fn version(conn: &Connection) -> Option<usize> {
         }
     }
 }
+
+/// Hash of the content saved for the card or None if the card is not in the timeline.
+pub fn card_hash(conn: &Connection, card: &Face) -> Option<hash::Hash> {
+    let row = conn.query_row(
+        "select content_sha256 from card where card_name = ?1;",
+        params![card.name()],
+        |row| {
+            let hash: String = row.get(0)?;
+            Ok(hash)
+        }
+    );
+
+    return match row {
+        Ok(hash) => hash::Hash::from_text(&hash).ok(),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::commit;
+    use crate::model::schema::TestTimeline;
+
+    #[test]
+    fn test_save_card_to_unknown_commit() {
+        let timeline = TestTimeline::new("unknown-commit");
+        let conn = &timeline.conn;
+        let card = NamingScheme::Alphanumeric.parse("1").unwrap();
+        let hash = hash::Hash::reader(&mut &b"one"[..], hash::Algorithm::Sha256).unwrap();
+
+        assert!(save_card_and_hash(conn, &card, &hash, 42).is_err());
+        assert!(card_hash(conn, &card).is_none());
+
+        let commit_id = commit::create_commit(conn, "first", "test").unwrap();
+        assert_eq!(save_card_and_hash(conn, &card, &hash, commit_id), Ok(()));
+        assert_eq!(card_hash(conn, &card), Some(hash));
+    }
+
+    #[test]
+    fn test_release_stale_reservations() {
+        let timeline = TestTimeline::new("stale-reservations");
+        let conn = &timeline.conn;
+        let stale = NamingScheme::Alphanumeric.parse("1").unwrap();
+        let fresh = NamingScheme::Alphanumeric.parse("2").unwrap();
+
+        conn.execute(
+            "insert into card_reservation(card_name, reserved_time) values ('1', '2026-01-01T00:00:00Z');",
+            params![]).unwrap();
+        assert_eq!(reserve_card(conn, &stale), Ok(false));
+        assert_eq!(reserve_card(conn, &fresh), Ok(true));
+
+        let before = Timestamp::parse("2026-06-01").unwrap();
+        assert_eq!(release_stale_reservations(conn, &before), Ok(1));
+        let names = timeline_card_names(conn).unwrap();
+        assert!(!names.contains("1"));
+        assert!(names.contains("2"));
+        assert_eq!(reserve_card(conn, &stale), Ok(true));
+    }
+}
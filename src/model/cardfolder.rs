@@ -1,24 +1,36 @@
 use std::path::{PathBuf, Path};
 use std::fs;
 use std::io;
+use std::collections::HashSet;
 use crate::card::Face;
 use rusqlite::{Connection, params};
 use crate::card;
+use crate::card::config::CardConfig;
+use crate::card::layout::FolderLayout;
+use crate::card::pattern::FilePattern;
+use crate::model::carddb;
+use crate::model::ignore::IgnoreRules;
 
 pub struct CardFolder {
-    pub folder: PathBuf
+    pub folder: PathBuf,
+    pub config: CardConfig,
 }
 
 impl CardFolder {
-    pub fn new(folder: PathBuf) -> CardFolder {
+    pub fn new(folder: PathBuf, config: CardConfig) -> CardFolder {
         if folder.is_dir() {
-            return CardFolder { folder };
+            return CardFolder { folder, config };
         } else {
             // Note(wistrandj): This is not the way to handle errors.
             panic!("The default card folder is missing");
         }
     }
 
+    /// The given card folder in the card configuration of the timeline.
+    pub fn open(folder: &Path, timeline: &Connection) -> CardFolder {
+        return Self::new(folder.to_path_buf(), carddb::card_config(timeline));
+    }
+
     pub fn from_timeline(timeline: &Connection) -> CardFolder {
         let row = timeline.query_row(
             "select default_location from configuration",
@@ -30,69 +42,122 @@ impl CardFolder {
             });
 
         if let Ok(path) = row {
-            let folder = PathBuf::from(path);
-            return Self::new(folder);
+            let folder = path;
+            return Self::new(folder, carddb::card_config(timeline));
         } else {
             panic!("Bug: The default card folder is not configured");
         }
     }
 
     pub fn cards(&self) -> Vec<card::Face> {
-        let cardnames = list_file_names(&self.folder);
+        return self.card_files().into_iter().map(|(card, _)| card).collect();
+    }
+
+    /// The cards and their files. Cheaper than Face::location_in(..) for
+    /// every card when the file pattern has a slug.
+    pub fn card_files(&self) -> Vec<(card::Face, PathBuf)> {
+        let cardnames = list_file_names(&self.folder, &self.config.layout);
 
         if let Ok(cardnames) = cardnames {
-            let cards: Vec<Face> = cardnames.iter()
-                .map(|it| card::Face::from_name(it))
-                .filter(|it| it.is_some())
-                .map(|it| it.unwrap())
+            let cards: Vec<(Face, PathBuf)> = cardnames.iter()
+                .filter_map(|it| self.config.card_of_file(it).map(|card| (card, self.folder.join(it))))
                 .collect();
             return cards;
         } else {
             panic!("No cards");
         }
     }
+
+    /// Plan the renames of the card files from the file pattern of the
+    /// folder to the given one. A card keeps its slug, or gets the slug of its
+    /// title if it has none. Refuse if a new file name is taken.
+    pub fn renames_to_pattern(&self, pattern: &FilePattern) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+        let mut renames = Vec::new();
+        for (card, file) in self.card_files() {
+            let file_name = file.file_name().unwrap().to_string_lossy().to_string();
+            let (_, mut slug) = self.config.pattern.parse(&self.config.scheme, &file_name).unwrap();
+            if slug.is_empty() && pattern.has_slug() {
+                let text = fs::read(&file).map_err(|_| format!("Fail to read the card {}", card.name()))?;
+                slug = card::pattern::slug_of(&String::from_utf8_lossy(&text));
+            }
+            let new_file = file.with_file_name(pattern.file_name(&card, &slug));
+            if new_file != file {
+                renames.push((file, new_file));
+            }
+        }
+
+        let sources: HashSet<&PathBuf> = renames.iter().map(|(file, _)| file).collect();
+        let mut targets = HashSet::new();
+        for (_, new_file) in &renames {
+            let taken = new_file.exists() && !sources.contains(new_file);
+            if taken || !targets.insert(new_file) {
+                return Err(format!("Cannot rename the card files: {} exists", new_file.to_string_lossy()));
+            }
+        }
+        return Ok(renames);
+    }
+
+    /// Names of the files in the card folder that are not cards. The files in
+    /// the subfolders of the layout are like 0-999/notes.
+    pub fn other_files(&self) -> Vec<String> {
+        let names = list_file_names(&self.folder, &self.config.layout);
+
+        if let Ok(names) = names {
+            return names.into_iter()
+                .filter(|it| self.config.card_of_file(it).is_none())
+                .collect();
+        } else {
+            panic!("No cards");
+        }
+    }
 }
 
-pub fn next_available(cards: &Vec<card::Face>) -> Face {
-    let latest_card = cards.iter().max();
-    let latest_number: usize = if let Some(card) = latest_card { card.major_number() } else { 0 };
-    return Face::from_number(latest_number + 1);
+/// The first card from the given one onwards whose name is not taken,
+/// following the next siblings: 123a1, 123a2, 123a3...
+pub fn next_free_sibling(first: Face, taken: &HashSet<String>) -> Face {
+    let mut card = first;
+    while taken.contains(&card.name()) {
+        card = card.next_sibling();
+    }
+    return card;
 }
 
-pub fn next_major_card(folder: &PathBuf) -> Face {
-    let cards = list_cards(folder);
-    let latest_card = cards.iter().max();
-    let latest_number: usize = if let Some(card) = latest_card { card.major_number() } else { 0 };
-    return Face::from_number(latest_number + 1);
+/// The major card after the cards in the folder and every card name the
+/// timeline knows. See carddb::timeline_card_names(..).
+pub fn next_major_card(opencards: &CardFolder, conn: &Connection) -> Result<Face, String> {
+    let mut cards = opencards.cards();
+    let names = carddb::timeline_card_names(conn)?;
+    cards.extend(names.iter().filter_map(|name| opencards.config.card(name)));
+    return Ok(opencards.config.scheme.next_major(&cards));
 }
 
-fn list_file_names(path: &Path) -> io::Result<Vec<String>> {
+/// Names of the files in the folder and in the subfolders of the folder
+/// layout, like 123a and 0-999/123a. The files ignored by the .zkignore file
+/// of the folder are left out.
+fn list_file_names(path: &Path, layout: &FolderLayout) -> io::Result<Vec<String>> {
     let mut files = Vec::new();
+    let rules = IgnoreRules::load(path);
 
     for entry in fs::read_dir(path)? {
         let entry = entry?;
         let filetype = entry.file_type()?;
-        if filetype.is_dir() || filetype.is_symlink() {
+        let filename = entry.file_name().to_string_lossy().to_string();
+        if rules.is_ignored(&filename, filetype.is_dir()) {
+            continue;
+        } else if filetype.is_dir() && layout.is_subfolder(&filename) {
+            for subentry in fs::read_dir(entry.path())? {
+                let subentry = subentry?;
+                let subname = format!("{}/{}", filename, subentry.file_name().to_string_lossy());
+                if subentry.file_type()?.is_file() && !rules.is_ignored(&subname, false) {
+                    files.push(subname);
+                }
+            }
+        } else if filetype.is_dir() || filetype.is_symlink() {
             continue;
         } else if filetype.is_file() {
-            let path = entry.path();
-            let filename = path.file_name().unwrap().to_str().unwrap();
-            files.push(String::from(filename));
+            files.push(filename);
         }
     }
 
     return Ok(files);
 }
-
-fn list_cards(path: &Path) -> Vec<Face> {
-    if let Ok(files) = list_file_names(path) {
-        return files.iter()
-            .map(|file| Face::from_name(file))
-            .filter(|card| !card.is_none())
-            .map(|card| card.unwrap())
-            .collect();
-    } else {
-        return vec![];
-    }
-}
-
@@ -0,0 +1,155 @@
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use std::io;
+use std::io::{Read, Write};
+
+/// How the bytes of a blob are stored. The hash of a blob is always the
+/// hash of the decoded bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Codec {
+    Identity,
+    Deflate,
+}
+
+impl Codec {
+    /// Name in the codec column. Identity is stored as null.
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Codec::Identity => None,
+            Codec::Deflate => Some("deflate"),
+        }
+    }
+
+    pub fn from_name(name: Option<&str>) -> Result<Codec, String> {
+        match name {
+            None | Some("none") => Ok(Codec::Identity),
+            Some("deflate") => Ok(Codec::Deflate),
+            Some(other) => Err(format!("Unknown codec: {}", other)),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        self.name().unwrap_or("none")
+    }
+
+    pub fn encoder<'a>(&self, output: &'a mut dyn Write) -> Encoder<'a> {
+        match self {
+            Codec::Identity => Encoder::Identity(output),
+            Codec::Deflate => Encoder::Deflate(DeflateEncoder::new(output, Compression::default())),
+        }
+    }
+
+    pub fn decoder<'a>(&self, input: Box<dyn Read + 'a>) -> Box<dyn Read + 'a> {
+        match self {
+            Codec::Identity => input,
+            Codec::Deflate => Box::new(DeflateDecoder::new(input)),
+        }
+    }
+}
+
+impl FromSql for Codec {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Codec> {
+        let name = match value {
+            ValueRef::Null => None,
+            ValueRef::Text(text) => Some(std::str::from_utf8(text).map_err(|_| FromSqlError::InvalidType)?),
+            _ => return Err(FromSqlError::InvalidType),
+        };
+        return Codec::from_name(name).map_err(|msg| FromSqlError::Other(msg.into()));
+    }
+}
+
+/// Encode everything written into it. Call finish(..) to write the end of
+/// the encoded stream.
+pub enum Encoder<'a> {
+    Identity(&'a mut dyn Write),
+    Deflate(DeflateEncoder<&'a mut dyn Write>),
+}
+
+impl<'a> Encoder<'a> {
+    pub fn finish(self) -> Result<(), io::Error> {
+        match self {
+            Encoder::Identity(output) => output.flush(),
+            Encoder::Deflate(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl<'a> Write for Encoder<'a> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, io::Error> {
+        match self {
+            Encoder::Identity(output) => output.write(data),
+            Encoder::Deflate(encoder) => encoder.write(data),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        match self {
+            Encoder::Identity(output) => output.flush(),
+            Encoder::Deflate(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Count the bytes written and fail if there are more than the limit.
+pub struct Counter<'a> {
+    output: Option<&'a mut dyn Write>,
+    limit: Option<u64>,
+    pub count: u64,
+}
+
+impl<'a> Counter<'a> {
+    /// Count without writing anywhere.
+    pub fn sink() -> Counter<'a> {
+        Counter { output: None, limit: None, count: 0 }
+    }
+
+    pub fn new(output: &'a mut dyn Write, limit: Option<u64>) -> Counter<'a> {
+        Counter { output: Some(output), limit, count: 0 }
+    }
+}
+
+impl<'a> Write for Counter<'a> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, io::Error> {
+        if self.limit.is_some_and(|limit| self.count + data.len() as u64 > limit) {
+            return Err(io::Error::other("More data than expected"));
+        }
+        let count = match &mut self.output {
+            Some(output) => output.write(data)?,
+            None => data.len(),
+        };
+        self.count += count as u64;
+        return Ok(count);
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        match &mut self.output {
+            Some(output) => output.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = "A card about cards. ".repeat(100);
+        for codec in [Codec::Identity, Codec::Deflate].iter() {
+            let mut encoded = Vec::new();
+            let mut encoder = codec.encoder(&mut encoded);
+            encoder.write_all(text.as_bytes()).unwrap();
+            encoder.finish().unwrap();
+
+            let mut decoded = String::new();
+            codec.decoder(Box::new(encoded.as_slice())).read_to_string(&mut decoded).unwrap();
+            assert_eq!(decoded, text);
+            assert_eq!(Codec::from_name(codec.name()), Ok(*codec));
+        }
+        assert!(Codec::from_name(Some("zip")).is_err());
+    }
+}
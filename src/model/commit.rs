@@ -0,0 +1,26 @@
+use rusqlite::{Connection, params};
+use crate::card::Timestamp;
+use std::env;
+
+/// Name of the user recorded in commits. Use $ZK_AUTHOR or fall back to $USER.
+pub fn author() -> String {
+    if let Ok(author) = env::var("ZK_AUTHOR") {
+        return author;
+    }
+    if let Ok(user) = env::var("USER") {
+        return user;
+    }
+    return String::from("unknown");
+}
+
+/// Create a new commit and return its id.
+pub fn create_commit(conn: &Connection, message: &str, author: &str) -> Result<usize, &'static str> {
+    let now = Timestamp::now();
+    let sql = "insert into commit_log(message, author, commit_time) values (?1, ?2, ?3);";
+    let args = params![message, author, now.to_string()];
+    let success = conn.execute(sql, args);
+    match success {
+        Ok(_) => Ok(conn.last_insert_rowid() as usize),
+        Err(_) => Err("Fail to create a commit"),
+    }
+}
@@ -0,0 +1,243 @@
+use std::fs;
+use std::path::Path;
+
+/// Name of the file of ignore rules in the card folder.
+pub const IGNORE_FILE: &str = ".zkignore";
+
+/// Files of the card folder that are neither cards nor other files, like
+/// editor swap files, .git or README. The rules are globs like in
+/// .gitignore, one per line:
+///
+///   # Vim swap files anywhere
+///   *.swp
+///   # The folder .git and everything in it
+///   .git/
+///   # Only the README in the card folder itself
+///   /README*
+///   # Not this one although it matches a rule above
+///   !keep.swp
+///
+/// A `*` matches anything but a slash, `**` matches anything, `?` one
+/// character and `[a-z]` one character of the class. A rule without a slash
+/// matches the file name at any level. Otherwise it matches the path from the
+/// card folder. The last matching rule wins. A file in an ignored folder is
+/// ignored whatever the rules say about the file.
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+pub struct Rule {
+    /// Line number in the ignore file starting from 1.
+    pub line: usize,
+    /// The rule as written.
+    pub text: String,
+    pub negated: bool,
+    dir_only: bool,
+    anchored: bool,
+    glob: String,
+}
+
+/// Why a path is or is not ignored.
+pub enum Reason<'a> {
+    /// The ignore file itself.
+    IgnoreFile,
+    /// The last rule that matches the path or one of its folders.
+    Rule { rule: &'a Rule, path: String },
+    /// No rule matches.
+    NoRule,
+}
+
+impl<'a> Reason<'a> {
+    pub fn is_ignored(&self) -> bool {
+        match self {
+            Reason::IgnoreFile => true,
+            Reason::Rule { rule, .. } => !rule.negated,
+            Reason::NoRule => false,
+        }
+    }
+}
+
+impl IgnoreRules {
+    /// The rules of the ignore file in the folder. No rules if there is none.
+    pub fn load(folder: &Path) -> IgnoreRules {
+        let text = fs::read_to_string(folder.join(IGNORE_FILE)).unwrap_or_default();
+        return IgnoreRules::parse(&text);
+    }
+
+    pub fn parse(text: &str) -> IgnoreRules {
+        let mut rules = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let text = line.trim_end();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+
+            let mut glob = text;
+            let negated = glob.starts_with('!');
+            glob = glob.trim_start_matches('!');
+            // Note(wistrandj): \# and \! are a # and a ! at the start.
+            if glob.starts_with("\\#") || glob.starts_with("\\!") {
+                glob = &glob[1..];
+            }
+            let dir_only = glob.ends_with('/');
+            glob = glob.trim_end_matches('/');
+            let anchored = glob.contains('/');
+            glob = glob.trim_start_matches('/');
+            if glob.is_empty() {
+                continue;
+            }
+
+            rules.push(Rule { line: i + 1, text: String::from(text), negated, dir_only, anchored, glob: String::from(glob) });
+        }
+        return IgnoreRules { rules };
+    }
+
+    /// The last rule that matches the path relative to the card folder.
+    fn last_match(&self, path: &str, is_dir: bool) -> Option<&Rule> {
+        let name = path.rsplit('/').next().unwrap();
+        return self.rules.iter().rev().find(|rule| {
+            if rule.dir_only && !is_dir {
+                return false;
+            }
+            let text = if rule.anchored { path } else { name };
+            glob_match(rule.glob.as_bytes(), text.as_bytes())
+        });
+    }
+
+    /// Why the path relative to the card folder, like 0-999/123a.swp, is or
+    /// is not ignored.
+    pub fn explain(&self, path: &str, is_dir: bool) -> Reason<'_> {
+        if path == IGNORE_FILE {
+            return Reason::IgnoreFile;
+        }
+
+        // The folders first. A file in an ignored folder cannot be brought back.
+        let parts: Vec<&str> = path.split('/').collect();
+        for end in 1..parts.len() {
+            let folder = parts[..end].join("/");
+            if let Some(rule) = self.last_match(&folder, true) {
+                if !rule.negated {
+                    return Reason::Rule { rule, path: folder };
+                }
+            }
+        }
+
+        match self.last_match(path, is_dir) {
+            Some(rule) => Reason::Rule { rule, path: String::from(path) },
+            None => Reason::NoRule,
+        }
+    }
+
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        return self.explain(path, is_dir).is_ignored();
+    }
+}
+
+/// Whether the text matches the glob. See IgnoreRules.
+fn glob_match(glob: &[u8], text: &[u8]) -> bool {
+    match glob.first() {
+        None => text.is_empty(),
+        Some(b'*') if glob.starts_with(b"**") => {
+            // **/ matches no folder too: **/x matches x.
+            let rest = &glob[2..];
+            if rest.starts_with(b"/") && glob_match(&rest[1..], text) {
+                return true;
+            }
+            return (0..=text.len()).any(|i| glob_match(rest, &text[i..]));
+        },
+        Some(b'*') => {
+            let rest = &glob[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == b'/' {
+                    break;
+                }
+            }
+            return false;
+        },
+        Some(b'?') => !text.is_empty() && text[0] != b'/' && glob_match(&glob[1..], &text[1..]),
+        Some(b'[') => {
+            let end = glob.iter().skip(2).position(|c| *c == b']').map(|end| end + 2);
+            match (end, text.first()) {
+                (Some(end), Some(c)) => class_match(&glob[1..end], *c) && glob_match(&glob[end + 1..], &text[1..]),
+                (Some(_), None) => false,
+                // A [ without ] is just a [.
+                (None, _) => text.first() == Some(&b'[') && glob_match(&glob[1..], &text[1..]),
+            }
+        },
+        Some(b'\\') if glob.len() > 1 => text.first() == Some(&glob[1]) && glob_match(&glob[2..], &text[1..]),
+        Some(c) => text.first() == Some(c) && glob_match(&glob[1..], &text[1..]),
+    }
+}
+
+/// Whether the character is in the class like a-z0-9 or !abc.
+fn class_match(class: &[u8], c: u8) -> bool {
+    let (negated, class) = match class.first() {
+        Some(b'!') | Some(b'^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == b'-' {
+            found |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    return found != negated && c != b'/';
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        let matches = |glob: &str, text: &str| glob_match(glob.as_bytes(), text.as_bytes());
+        assert!(matches("*.swp", ".123a.swp"));
+        assert!(!matches("*.swp", "0-999/123a.swp"));
+        assert!(matches("**/*.swp", "0-999/123a.swp"));
+        assert!(matches("**/*.swp", "123a.swp"));
+        assert!(matches("0-999/**", "0-999/a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(matches("README?", "README2"));
+        assert!(!matches("README?", "README"));
+        assert!(matches("[0-9]*~", "123a~"));
+        assert!(!matches("[!0-9]*~", "123a~"));
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "x"));
+        assert!(matches("a[b", "a[b"));
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = IgnoreRules::parse("# Editors\n*.swp\n!keep.swp\n\n.git/\n/README*\nexport/\n!export/x\n");
+        let ignored = |path: &str| rules.is_ignored(path, false);
+        assert!(ignored(".123a.swp"));
+        assert!(ignored("0-999/.123a.swp"));
+        assert!(!ignored("keep.swp"));
+        assert!(ignored("README.md"));
+        assert!(!ignored("0-999/README.md"));
+        assert!(ignored(".git/config"));
+        assert!(!ignored(".git"));
+        assert!(rules.is_ignored(".git", true));
+        assert!(ignored("export/x"));
+        assert!(ignored(".zkignore"));
+        assert!(!ignored("123a"));
+
+        match rules.explain("0-999/a.swp", false) {
+            Reason::Rule { rule, path } => assert_eq!((rule.line, rule.text.as_str(), path.as_str()), (2, "*.swp", "0-999/a.swp")),
+            _ => panic!("Not ignored by a rule"),
+        }
+        match rules.explain("keep.swp", false) {
+            Reason::Rule { rule, .. } => assert!(rule.negated && rule.line == 3),
+            _ => panic!("Not matched by a rule"),
+        }
+    }
+}
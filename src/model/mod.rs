@@ -1,6 +1,11 @@
+pub mod attachment;
 pub mod blob;
 pub mod carddb;
+pub mod commit;
+pub mod revision;
 pub mod cardfolder;
+pub mod codec;
+pub mod ignore;
 pub mod schema;
 pub mod tag;
 
@@ -0,0 +1,62 @@
+use rusqlite::{Connection, params};
+use crate::card::{Face, Timestamp};
+
+pub struct Revision {
+    /// Running number of the revision within the card. The first revision is 1.
+    pub number: usize,
+    pub content_sha256: String,
+    pub revision_time: Timestamp,
+    pub author: Option<String>,
+    pub commit_message: Option<String>,
+}
+
+/// All revisions of the card, newest first.
+pub fn revisions_of_card(conn: &Connection, card: &Face) -> Result<Vec<Revision>, &'static str> {
+    let sql = "
+        select content_sha256, revision_time, author, commit_message
+        from card_revision
+        where card_name = ?1
+        order by revision_id;";
+    let stmt = conn.prepare(sql);
+    if stmt.is_err() {
+        return Err("Fail to prepare a query");
+    }
+    let mut stmt = stmt.unwrap();
+
+    let rows = stmt.query_map(params![card.name()], |row| {
+        Ok(Revision {
+            number: 0,
+            content_sha256: row.get(0)?,
+            revision_time: Timestamp::parse_or_unknown(row.get(1)?),
+            author: row.get(2)?,
+            commit_message: row.get(3)?,
+        })
+    });
+    if rows.is_err() {
+        return Err("Fail to read card revisions");
+    }
+
+    let mut revisions = Vec::new();
+    for (i, row) in rows.unwrap().enumerate() {
+        if let Ok(mut revision) = row {
+            revision.number = i + 1;
+            revisions.push(revision);
+        } else {
+            return Err("Fail to read card revisions");
+        }
+    }
+
+    revisions.reverse();
+    return Ok(revisions);
+}
+
+/// The revision with the given running number.
+pub fn find_revision(conn: &Connection, card: &Face, number: usize) -> Result<Revision, &'static str> {
+    let revisions = revisions_of_card(conn, card)?;
+    for revision in revisions {
+        if revision.number == number {
+            return Ok(revision);
+        }
+    }
+    return Err("No such revision");
+}
@@ -1,15 +1,29 @@
 use rusqlite::{Connection, params};
 use crate::feature as feature;
-use std::collections::HashSet;
 
 
 pub fn install_missing_features(conn: &mut Connection) {
-    if !feature::has_feature_table("feature", &conn) {
-        feature::create_feature_table(&conn);
+    if !feature::has_feature_table("feature", conn) {
+        feature::create_feature_table(conn);
     }
+    // Note(wistrandj): Timelines created before the features were recorded
+    // have the tables of setup1, setup2 and tag but no rows in the feature
+    // table. Those features use "create table if not exists".
     feature::enable_feature("setup1", conn, &Setup1 {});
     feature::enable_feature("setup2", conn, &Setup2 {});
     feature::enable_feature("tag", conn, &Tag {});
+    feature::enable_feature("commit", conn, &Commit {});
+    feature::enable_feature("revision", conn, &Revision {});
+    feature::enable_feature("card_time", conn, &CardTime {});
+    feature::enable_feature("content_time", conn, &ContentTime {});
+    feature::enable_feature("attachment", conn, &Attachment {});
+    feature::enable_feature("sidecar", conn, &Sidecar {});
+    feature::enable_feature("codec", conn, &Codec {});
+    feature::enable_feature("hash_algorithm", conn, &HashAlgorithm {});
+    feature::enable_feature("naming_scheme", conn, &NamingScheme {});
+    feature::enable_feature("file_pattern", conn, &FilePattern {});
+    feature::enable_feature("folder_layout", conn, &FolderLayout {});
+    feature::enable_feature("card_reservation", conn, &CardReservation {});
 }
 
 struct Setup1 {}
@@ -17,7 +31,7 @@ impl feature::Feature for Setup1 {
     fn enable(&self, conn: &mut Connection) {
         let success = conn.execute(
             "
-            create table configuration (
+            create table if not exists configuration (
                 version integer,
                 default_location text
             );
@@ -48,11 +62,11 @@ impl feature::Feature for Setup2 {
     fn enable(&self, conn: &mut Connection) {
         let success = conn.execute_batch(
             "
-            create table content(
+            create table if not exists content(
                 content_sha256 text not null,
                 blob blob
             );
-            create table card(
+            create table if not exists card(
                 card_name text primary key, -- '123', '123a1'
                 content_sha256 text
             );
@@ -82,12 +96,12 @@ impl feature::Feature for Tag {
     fn enable(&self, conn: &mut Connection) {
         let success = conn.execute_batch(
             "
-            create table tag (
+            create table if not exists tag (
                 tag_name text not null,
                 major_card_number integer not null,
                 unique(tag_name, major_card_number)
             );
-            create table tag_history (
+            create table if not exists tag_history (
                 batch_id integer not null,
                 tag_name text not null,
                 major_card_number integer not null
@@ -113,3 +127,380 @@ impl feature::Feature for Tag {
     }
 }
 
+pub struct Commit {}
+
+impl feature::Feature for Commit {
+    fn enable(&self, conn: &mut Connection) {
+        let success = conn.execute_batch(
+            "
+            create table commit_log (
+                commit_id integer primary key,
+                message text not null,
+                author text not null,
+                commit_time text not null
+            );
+            alter table card add column commit_id integer;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to create commit table. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, conn: &mut Connection) {
+        let success = conn.execute_batch(
+            "
+            drop table commit_log;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to delete commit table. Reason: {}", msg);
+        }
+    }
+}
+
+pub struct Revision {}
+
+impl feature::Feature for Revision {
+    fn enable(&self, conn: &mut Connection) {
+        // Existing cards get their current content as the first revision.
+        let success = conn.execute_batch(
+            "
+            create table card_revision (
+                revision_id integer primary key,
+                card_name text not null,
+                content_sha256 text not null,
+                commit_id integer,
+                revision_time text,
+                author text,
+                commit_message text
+            );
+            insert into card_revision(card_name, content_sha256, commit_id, revision_time, author, commit_message)
+                select card.card_name, card.content_sha256, card.commit_id,
+                       commit_log.commit_time, commit_log.author, commit_log.message
+                from card left join commit_log on card.commit_id = commit_log.commit_id;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to create card revision table. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, conn: &mut Connection) {
+        let success = conn.execute_batch(
+            "
+            drop table card_revision;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to delete card revision table. Reason: {}", msg);
+        }
+    }
+}
+
+pub struct CardTime {}
+
+impl feature::Feature for CardTime {
+    fn enable(&self, conn: &mut Connection) {
+        let success = conn.execute_batch(
+            "
+            alter table card add column create_time text;
+            alter table card add column modify_time text;
+            update card set
+                create_time = (select min(revision_time) from card_revision where card_revision.card_name = card.card_name),
+                modify_time = (select max(revision_time) from card_revision where card_revision.card_name = card.card_name);
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to add times to the card table. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, _conn: &mut Connection) {
+        // Note(wistrandj): Sqlite cannot drop columns. Leave them be.
+    }
+}
+
+pub struct ContentTime {}
+
+impl feature::Feature for ContentTime {
+    fn enable(&self, conn: &mut Connection) {
+        // Blobs stored before this feature have no time. They count as old.
+        let success = conn.execute_batch(
+            "
+            alter table content add column stored_time text;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to add the stored time to the content table. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, _conn: &mut Connection) {
+        // Note(wistrandj): Sqlite cannot drop columns. Leave them be.
+    }
+}
+
+pub struct Attachment {}
+
+impl feature::Feature for Attachment {
+    fn enable(&self, conn: &mut Connection) {
+        let success = conn.execute_batch(
+            "
+            create table attachment (
+                card_name text not null,
+                file_name text not null,
+                mime_type text not null,
+                content_sha256 text not null,
+                unique(card_name, file_name)
+            );
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to create attachment table. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, conn: &mut Connection) {
+        let success = conn.execute_batch(
+            "
+            drop table attachment;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to delete attachment table. Reason: {}", msg);
+        }
+    }
+}
+
+pub struct Sidecar {}
+
+impl feature::Feature for Sidecar {
+    fn enable(&self, conn: &mut Connection) {
+        // A blob stored in the objects directory has a null blob column and
+        // its size in the object_size column. No threshold means that every
+        // blob is stored in the timeline file.
+        let success = conn.execute_batch(
+            "
+            alter table content add column object_size integer;
+            alter table configuration add column sidecar_threshold integer;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to add the sidecar columns. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, _conn: &mut Connection) {
+        // Note(wistrandj): Sqlite cannot drop columns. Leave them be.
+    }
+}
+
+pub struct Codec {}
+
+impl feature::Feature for Codec {
+    fn enable(&self, conn: &mut Connection) {
+        // A null codec means the blob is stored as is. The blobs stored
+        // before this feature stay so until `zk blob recompress`. The
+        // default fills the configuration row of an existing timeline and
+        // the row that `zk init` inserts after the features alike.
+        let success = conn.execute_batch(
+            "
+            alter table content add column codec text;
+            alter table configuration add column codec text default 'deflate';
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to add the codec columns. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, _conn: &mut Connection) {
+        // Note(wistrandj): Sqlite cannot drop columns. Leave them be.
+    }
+}
+
+pub struct HashAlgorithm {}
+
+impl feature::Feature for HashAlgorithm {
+    fn enable(&self, conn: &mut Connection) {
+        // Note(wistrandj): The content_sha256 columns keep their names but
+        // hold hashes of any algorithm. See hash::Hash::to_string(..).
+        // Rehashing the content records the old hashes as aliases so that
+        // they can still be used.
+        let success = conn.execute_batch(
+            "
+            alter table configuration add column hash_algorithm text;
+            create table hash_alias (
+                old_id text primary key,
+                new_id text not null
+            );
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to add the hash algorithm. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, conn: &mut Connection) {
+        let success = conn.execute_batch(
+            "
+            drop table hash_alias;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to delete hash_alias table. Reason: {}", msg);
+        }
+    }
+}
+
+pub struct NamingScheme {}
+
+impl feature::Feature for NamingScheme {
+    fn enable(&self, conn: &mut Connection) {
+        // A null naming scheme is the alphanumeric one of the older timelines.
+        // See card::naming::NamingScheme.
+        let success = conn.execute_batch(
+            "
+            alter table configuration add column naming_scheme text;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to add the naming scheme. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, _conn: &mut Connection) {
+        // Note(wistrandj): Sqlite cannot drop columns. Leave them be.
+    }
+}
+
+pub struct FilePattern {}
+
+impl feature::Feature for FilePattern {
+    fn enable(&self, conn: &mut Connection) {
+        // A null file pattern names the card files as the cards. See
+        // card::pattern::FilePattern.
+        let success = conn.execute_batch(
+            "
+            alter table configuration add column file_pattern text;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to add the file pattern. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, _conn: &mut Connection) {
+        // Note(wistrandj): Sqlite cannot drop columns. Leave them be.
+    }
+}
+
+pub struct FolderLayout {}
+
+impl feature::Feature for FolderLayout {
+    fn enable(&self, conn: &mut Connection) {
+        // A null folder layout keeps every card file in the card folder. See
+        // card::layout::FolderLayout.
+        let success = conn.execute_batch(
+            "
+            alter table configuration add column folder_layout text;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to add the folder layout. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, _conn: &mut Connection) {
+        // Note(wistrandj): Sqlite cannot drop columns. Leave them be.
+    }
+}
+
+pub struct CardReservation {}
+
+impl feature::Feature for CardReservation {
+    fn enable(&self, conn: &mut Connection) {
+        // `zk card` reserves the name of the new card before the editor is
+        // opened. The reservation is removed when the card is saved.
+        let success = conn.execute_batch(
+            "
+            create table card_reservation (
+                card_name text primary key,
+                reserved_time text not null
+            );
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to create card_reservation table. Reason: {}", msg);
+        }
+    }
+    fn rollback(&self, conn: &mut Connection) {
+        let success = conn.execute_batch(
+            "
+            drop table card_reservation;
+            "
+        );
+
+        if let Err(msg) = success {
+            panic!("Fail to delete card_reservation table. Reason: {}", msg);
+        }
+    }
+}
+
+
+/// A timeline with every feature and an empty card folder in a new temporary
+/// folder, for the tests. The folder is removed when the timeline is dropped.
+#[cfg(test)]
+pub struct TestTimeline {
+    pub dir: std::path::PathBuf,
+    pub conn: Connection,
+}
+
+#[cfg(test)]
+impl TestTimeline {
+    /// The name tells apart the folders of the tests that run at once.
+    pub fn new(name: &str) -> TestTimeline {
+        let dir = std::env::temp_dir().join(format!("zk-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("cards")).unwrap();
+
+        let mut conn = Connection::open(dir.join("timeline")).unwrap();
+        install_missing_features(&mut conn);
+        conn.execute(
+            "insert into configuration(version, default_location) values (1, ?1);",
+            params![dir.join("cards").to_string_lossy()]
+        ).unwrap();
+        TestTimeline { dir, conn }
+    }
+
+    pub fn cards(&self) -> std::path::PathBuf {
+        return self.dir.join("cards");
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestTimeline {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::carddb;
+    use crate::model::codec;
+
+    #[test]
+    fn test_default_codec() {
+        // The configuration row of a new timeline is inserted after the features.
+        let timeline = TestTimeline::new("default-codec");
+        assert_eq!(carddb::codec(&timeline.conn), codec::Codec::Deflate);
+    }
+}
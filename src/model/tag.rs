@@ -16,7 +16,7 @@ impl<'a> TagFeature<'a> {
 
         let sql = "select tag_name from tag order by 1;";
         let args = params![];
-        let mut stmt = self.connection.prepare(sql);
+        let stmt = self.connection.prepare(sql);
         if let Ok(mut stmt) = stmt {
             let tags_result = stmt.query_map(args,
                 |row| {
@@ -38,6 +38,27 @@ impl<'a> TagFeature<'a> {
         }
     }
 
+    /// Every tag with the card it is set to.
+    pub fn all_tagged_cards(&self) -> Result<Vec<(String, usize)>, &'static str> {
+        let sql = "select tag_name, major_card_number from tag order by 1, 2;";
+        let stmt = self.connection.prepare(sql);
+        if let Ok(mut stmt) = stmt {
+            let rows = stmt.query_map(params![], |row| {
+                let tag_name: String = row.get(0)?;
+                let major_card_number: u32 = row.get(1)?;
+                return Ok((tag_name, major_card_number as usize));
+            }).unwrap();
+
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(row.unwrap());
+            }
+            return Ok(result);
+        } else {
+            return Err("Fail to prepare a query");
+        }
+    }
+
     /// Given a list of cards, find all tags associated to them.
     pub fn find_tags_of_cards(&self, major_card_numbers: &Vec<usize>) -> Result<Vec<String>, &'static str> {
         let mut found_tags = HashSet::new();
@@ -112,7 +133,7 @@ impl<'a> TagFeature<'a> {
         let tag_exists = self.connection.query_row(sql, args,
             |row| {
                 let matching_rows: u32 = row.get(0)?;
-                println!("{}", format!("> tags for {} are count of {}", major_card_number, matching_rows));
+                println!("> tags for {} are count of {}", major_card_number, matching_rows);
                 return Ok(matching_rows > 0);
             });
         match tag_exists {
@@ -155,6 +176,7 @@ impl<'a> TagFeature<'a> {
     }
 
     /// Latest batch id number or zero if no batches available.
+    #[allow(dead_code)]
     pub fn latest_batch_id_or_zero(&self) -> Result<usize, &'static str> {
         let sql = "select coalesce(max(batch_id), 0) as latest_batch_id from tag_history;";
         let args = params![];
@@ -172,6 +194,7 @@ impl<'a> TagFeature<'a> {
     }
 
     /// Insert a tag into a history as a part of a batch.
+    #[allow(dead_code)]
     pub fn insert_tag_to_a_batch_history(&self, batch_id: usize, tag_name: &str, major_card_number: usize) -> Result<(), &'static str> {
         let sql = "insert into tag_history(batch_id, tag_name, major_card_number) values (?1, ?2, ?3);";
         let args = params![batch_id as u32, tag_name, major_card_number as u32];
@@ -183,6 +206,7 @@ impl<'a> TagFeature<'a> {
     }
 
     /// Delete tags from cards in a given batch.
+    #[allow(dead_code)]
     pub fn delete_card_tags_in_a_batch(&self, batch_id: usize) -> Result<(), &'static str> {
         let sql = "delete from tag where (tag_name, major_card_number) in (select tag_name, card from tag_history where batch_id = ?1)";
         let args = params![batch_id as u32];
@@ -194,6 +218,7 @@ impl<'a> TagFeature<'a> {
     }
 
     /// Delete the batch from tag history.
+    #[allow(dead_code)]
     pub fn delete_batch_from_history(&self, batch_id: usize) -> Result<(), &'static str> {
         let sql = "delete from tag_history where batch_id = ?1";
         let args = params![batch_id as u32];
@@ -32,13 +32,16 @@ impl Args {
         }
 
         let all_commands = [
-            "init", "card", "add", "set", "blob", "tag"
+            "init", "card", "add", "set", "blob", "tag",
+            "status", "commit", "log", "ls", "diff", "restore", "checkout",
+            "gc", "fsck", "attach", "attachments", "detach",
+            "parent", "children", "next", "tree", "mv", "relayout", "check-ignore"
         ];
 
-        let subcommand = args.get(0).unwrap();
+        let subcommand = args.first().unwrap();
         if all_commands.contains(&subcommand.as_str()) {
             let subcommand = args.remove(0);
-            return Some(String::from(subcommand));
+            return Some(subcommand);
         } else {
             return None;
         }
@@ -52,7 +55,7 @@ pub fn get_timeline(args: &mut Vec<String>) -> Option<PathBuf> {
 
     for (i, arg) in args.iter().enumerate() {
         if arg == "--timeline" || arg == "-t" {
-            if let None = path_index {
+            if path_index.is_none() {
                 timeline_switch = Some(i);
                 path_index = Some(i + 1);
             } else {
@@ -90,9 +93,9 @@ mod test {
 
     #[test]
     fn test_positive1() {
-        let mut a1: Vec<String> = vec!["--timeline", "./timeline.db"].iter().map(|s| s.to_string()).collect();
-        let mut a2: Vec<String> = vec!["first", "--timeline", "./timeline.db"].iter().map(|s| s.to_string()).collect();
-        let mut a3: Vec<String> = vec!["first", "--timeline", "./timeline.db", "second"].iter().map(|s| s.to_string()).collect();
+        let mut a1: Vec<String> = ["--timeline", "./timeline.db"].iter().map(|s| s.to_string()).collect();
+        let mut a2: Vec<String> = ["first", "--timeline", "./timeline.db"].iter().map(|s| s.to_string()).collect();
+        let mut a3: Vec<String> = ["first", "--timeline", "./timeline.db", "second"].iter().map(|s| s.to_string()).collect();
 
         let path = get_timeline(&mut a1);
         assert_eq!(a1.len(), 0);
@@ -109,10 +112,10 @@ mod test {
 
     #[test]
     fn test_negative() {
-        let mut a1: Vec<String> = vec![];
-        let mut a2: Vec<String> = vec!["--timeline"].iter().map(|s| s.to_string()).collect();
-        let mut a3: Vec<String> = vec!["./timeline.db"].iter().map(|s| s.to_string()).collect();
-        let mut a4: Vec<String> = vec!["first", "--timeline"].iter().map(|s| s.to_string()).collect();
+        let a1: Vec<String> = vec![];
+        let a2: Vec<String> = ["--timeline"].iter().map(|s| s.to_string()).collect();
+        let a3: Vec<String> = ["./timeline.db"].iter().map(|s| s.to_string()).collect();
+        let a4: Vec<String> = ["first", "--timeline"].iter().map(|s| s.to_string()).collect();
 
         let mut alist = a1;
         let path = get_timeline(&mut alist);
@@ -147,17 +150,17 @@ mod test1 {
     use super::*;
     #[test]
     fn test_argument_read() {
-        let args = vec!["-t", "./timeline.db", "init"].iter().map(|s| s.to_string()).collect();
+        let args = ["-t", "./timeline.db", "init"].iter().map(|s| s.to_string()).collect();
         let args = Args::from_vec(args);
         assert_eq!(args.subcommand, Some("init".to_string()));
         assert_eq!(args.timeline_file, Some(PathBuf::from("./timeline.db")));
 
-        let args = vec!["init", "-t", "./timeline.db"].iter().map(|s| s.to_string()).collect();
+        let args = ["init", "-t", "./timeline.db"].iter().map(|s| s.to_string()).collect();
         let args = Args::from_vec(args);
         assert_eq!(args.subcommand, Some("init".to_string()));
         assert_eq!(args.timeline_file, Some(PathBuf::from("./timeline.db")));
 
-        let args = vec!["-t", "./timeline.db"].iter().map(|s| s.to_string()).collect();
+        let args = ["-t", "./timeline.db"].iter().map(|s| s.to_string()).collect();
         let args = Args::from_vec(args);
         assert_eq!(args.subcommand, None);
         assert_eq!(args.timeline_file, Some(PathBuf::from("./timeline.db")));
@@ -165,7 +168,7 @@ mod test1 {
 
     #[test]
     fn test_no_timeline() {
-        let args = vec!["init"].iter().map(|s| s.to_string()).collect();
+        let args = ["init"].iter().map(|s| s.to_string()).collect();
         let args = Args::from_vec(args);
         assert_eq!(args.subcommand, Some("init".to_string()));
         assert_eq!(args.timeline_file, None);
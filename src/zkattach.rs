@@ -0,0 +1,112 @@
+// Attach files to cards. Usage:
+//   $ zk -t ./timeline.zk attach 123a ./diagram.png
+//   $ zk -t ./timeline.zk attach 123a ./scan --mime application/pdf
+//   $ zk -t ./timeline.zk attachments 123a
+//   $ zk -t ./timeline.zk detach 123a diagram.png
+//
+// The files are stored in the timeline like any other content. Restore and
+// checkout write them into the folder "123a.attachments" next to the card.
+
+use std::path::{Path, PathBuf};
+use rusqlite::Connection;
+
+use super::varg;
+use super::card;
+use super::model;
+use super::model::{attachment, carddb, cardfolder};
+use super::control::attachment::AttachFile;
+
+fn card_argument(args: &varg::Args, timeline: &Connection) -> Option<card::Face> {
+    let card_name = args.args.first();
+    if card_name.is_none() {
+        eprintln!("Give a card");
+        return None;
+    }
+    let card_name = card_name.unwrap();
+    let face = carddb::card_config(timeline).card(card_name);
+    if face.is_none() {
+        eprintln!("Not a card name: {}", card_name);
+    }
+    return face;
+}
+
+pub fn zkattach(timeline_file: &Path, args: &varg::Args) {
+    let mut timeline = model::open_timeline(timeline_file).unwrap();
+    let face = card_argument(args, &timeline);
+    if face.is_none() {
+        return;
+    }
+    let face = face.unwrap();
+
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut mime_type: Option<String> = None;
+    let mut iter = args.args[1..].iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--mime" {
+            if let Some(value) = iter.next() {
+                mime_type = Some(String::from(value));
+            } else {
+                eprintln!("The --mime switch expects a mime type");
+                return;
+            }
+        } else {
+            files.push(PathBuf::from(arg));
+        }
+    }
+
+    if files.len() == 0 {
+        eprintln!("Give the files to attach");
+        return;
+    }
+
+    let opencards = cardfolder::CardFolder::from_timeline(&timeline);
+
+    for file in files {
+        let result = AttachFile::new(&mut timeline, &opencards.folder, face.clone(), &file, mime_type.as_deref())
+            .and_then(|cmd| cmd.call_once());
+        match result {
+            Ok(_) => println!("Attach {} to {}", file.to_string_lossy(), face.name()),
+            Err(msg) => eprintln!("{}", msg),
+        }
+    }
+}
+
+pub fn zkattachments(timeline_file: &Path, args: &varg::Args) {
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let face = card_argument(args, &timeline);
+    if face.is_none() {
+        return;
+    }
+    let face = face.unwrap();
+
+    match attachment::attachments_of_card(&timeline, &face) {
+        Ok(attachments) => {
+            for attachment in attachments {
+                println!("{}\t{}\t{}", attachment.file_name, attachment.mime_type, attachment.content_sha256);
+            }
+        },
+        Err(msg) => eprintln!("{}", msg),
+    }
+}
+
+pub fn zkdetach(timeline_file: &Path, args: &varg::Args) {
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let face = card_argument(args, &timeline);
+    if face.is_none() {
+        return;
+    }
+    let face = face.unwrap();
+
+    if args.args.len() < 2 {
+        eprintln!("Give the names of the attachments to detach");
+        return;
+    }
+
+    for file_name in &args.args[1..] {
+        match attachment::detach(&timeline, &face, file_name) {
+            Ok(true) => println!("Detach {} from {}", file_name, face.name()),
+            Ok(false) => eprintln!("The card {} has no attachment {}", face.name(), file_name),
+            Err(msg) => eprintln!("{}", msg),
+        }
+    }
+}
@@ -1,43 +1,151 @@
-use std::path::PathBuf;
+// Store and load files by their sha256. Usage:
+//   $ zk -t ./timeline.zk blob put ./photo.jpg ./notes.txt
+//   $ zk -t ./timeline.zk blob get 936a185c...07af > ./photo.jpg
+//   $ zk -t ./timeline.zk blob get 936a -o ./photo.jpg
+//   $ zk -t ./timeline.zk blob ls
+//   $ zk -t ./timeline.zk blob ls --full
+//   $ zk -t ./timeline.zk blob recompress
+//   $ zk -t ./timeline.zk blob recompress --codec none
+//   $ zk -t ./timeline.zk blob rehash blake3
+//
+// A blob can be given by a unique prefix of its hash of at least four digits.
+// The sizes are the stored sizes, after compression. Recompress stores the
+// blobs again with the codec of the timeline or the given codec. Rehash
+// identifies every blob by a hash of the given algorithm from now on. The old
+// hashes still find the blobs.
+
+use std::path::{Path, PathBuf};
 use super::varg;
 use super::model;
 use super::model::blob;
+use super::model::codec::Codec;
+use super::control::recompress::RecompressBlobs;
+use super::control::rehash::RehashContent;
+use super::hash;
+
+fn put(timeline_file: &Path, files: &[String]) {
+    let timeline = model::open_new_timeline(timeline_file).unwrap();
+
+    for filestr in files {
+        let file = PathBuf::from(filestr);
+        match blob::save(&timeline, &file) {
+            Ok(hash) => println!("{} {}", hash, file.to_string_lossy()),
+            Err(msg) => eprintln!("Fail to add file {:?}: {}", file, msg),
+        }
+    }
+}
+
+fn get(timeline_file: &Path, args: &[String]) -> Result<(), String> {
+    let mut hash_arg = None;
+    let mut output = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "-o" || arg == "--output" {
+            if let Some(file) = iter.next() {
+                output = Some(PathBuf::from(file));
+            } else {
+                return Err(String::from("Missing output file"));
+            }
+        } else if hash_arg.is_none() {
+            hash_arg = Some(arg);
+        } else {
+            return Err(format!("Invalid blob get argument: {}", arg));
+        }
+    }
+
+    if hash_arg.is_none() {
+        return Err(String::from("Give the sha256 of the blob"));
+    }
+
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let hash = blob::resolve_prefix(&timeline, hash_arg.unwrap())?;
+    let size = if let Some(output) = output {
+        blob::load_to_file(&timeline, &hash, &output)?
+    } else {
+        blob::load_to(&timeline, &hash, &mut std::io::stdout().lock())?
+    };
+
+    match size {
+        Some(_) => Ok(()),
+        None => Err(format!("No blob {}", hash)),
+    }
+}
 
-// Todo(wistrandj):
-// - solve the issue with varg module
-// - create subcommand for zkblob --put: save a file by it's sha256
-// - create subcommand for zkblob --get: load a file by it's sha256
+fn ls(timeline_file: &Path, args: &[String]) -> Result<(), String> {
+    let full = match args {
+        [] => false,
+        [arg] if arg == "--full" => true,
+        _ => return Err(String::from("Invalid blob ls arguments")),
+    };
 
-pub fn zkblob(timeline_file: &PathBuf, args: &varg::Args) {
-    let mut action_and_files = args.args.iter();
-    let action = action_and_files.next();
-    let mut files: Vec<PathBuf> = Vec::new();
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let mut blobs = blob::list(&timeline)?;
+    blobs.dedup_by(|a, b| a.content_sha256 == b.content_sha256);
+
+    // Abbreviate the hex digits. A prefix without the algorithm finds a
+    // hash of any algorithm so they must be unique among all of them.
+    let hex = |id: &str| -> String { id[id.find(':').map_or(0, |colon| colon + 1)..].to_string() };
+    blobs.sort_by_key(|info| hex(&info.content_sha256));
+    let hashes: Vec<String> = blobs.iter().map(|info| hex(&info.content_sha256)).collect();
+    let lengths = blob::unique_prefix_lengths(&hashes);
+
+    for (info, len) in blobs.iter().zip(lengths) {
+        let algorithm_len = info.content_sha256.len() - hex(&info.content_sha256).len();
+        let name = if full { &info.content_sha256[..] } else { &info.content_sha256[..algorithm_len + len] };
+        println!("{} {}", name, info.size);
+    }
+    return Ok(());
+}
+
+fn recompress(timeline_file: &Path, args: &[String]) -> Result<(), String> {
+    let mut timeline = model::open_timeline(timeline_file).unwrap();
+    let codec = match args {
+        [] => model::carddb::codec(&timeline),
+        [flag, name] if flag == "--codec" => Codec::from_name(Some(name))?,
+        _ => return Err(String::from("Invalid blob recompress arguments")),
+    };
+
+    let result = RecompressBlobs::new(&mut timeline, codec).call_once()?;
+    println!("Recompressed {} blobs with {}: {} bytes -> {} bytes",
+        result.blobs, codec.label(), result.bytes_before, result.bytes_after);
+    return Ok(());
+}
+
+fn rehash(timeline_file: &Path, args: &[String]) -> Result<(), String> {
+    let algorithm = match args {
+        [name] => hash::Algorithm::from_name(name)?,
+        _ => return Err(String::from("Give the hash algorithm: sha256 or blake3")),
+    };
+
+    let mut timeline = model::open_timeline(timeline_file).unwrap();
+    let count = RehashContent::new(&mut timeline, algorithm).call_once()?;
+    println!("Rehashed {} blobs with {}", count, algorithm.name());
+    return Ok(());
+}
+
+pub fn zkblob(timeline_file: &Path, args: &varg::Args) {
+    let action = args.args.first();
 
     if action.is_none() {
-        eprintln!("The blob subcommand expects more arguments. [put/get]");
+        eprintln!("The blob subcommand expects more arguments. [put/get/ls/recompress/rehash]");
         return;
     }
 
-    match action.unwrap().as_str() {
-        "get" => {
-            panic!("Not implemented yet.");
-        },
+    let rest = &args.args[1..];
+    let success = match action.unwrap().as_str() {
+        "get" => get(timeline_file, rest),
         "put" => {
-            for filestr in action_and_files {
-                let file = PathBuf::from(filestr);
-                files.push(file);
-            }
+            put(timeline_file, rest);
+            Ok(())
         },
-        _ => {
-            eprintln!("Invalid blob command [put/get]");
-            return;
-        }
-    }
-
-    let timeline = model::open_new_timeline(&timeline_file).unwrap();
+        "ls" => ls(timeline_file, rest),
+        "recompress" => recompress(timeline_file, rest),
+        "rehash" => rehash(timeline_file, rest),
+        _ => Err(String::from("Invalid blob command [put/get/ls/recompress/rehash]")),
+    };
 
-    for file in files {
-        blob::save(&timeline, &file);
-        println!("Add file {:?}", file);
+    if let Err(msg) = success {
+        eprintln!("{}", msg);
     }
 }
@@ -1,25 +1,138 @@
 // Usage:
 //   $ zk-card --timeline ./timeline.zk card
+//   $ zk-card --timeline ./timeline.zk card --under 123a
+//   $ zk-card --timeline ./timeline.zk card --after 123a
+//
+// Without options the card is the next major card. With --under it is the
+// next free card branching off the given card: 123a1, then 123a2. With
+// --after it is the next free card after the given one: 123b. A name is free
+// if it is neither in the card folder nor in the timeline, including the
+// history of deleted cards. The name is reserved in the timeline before the
+// editor is opened so that two zk card commands do not pick the same name.
+// A reservation expires after a day. By then the card file is in the folder
+// or the zk card that reserved the name has failed.
 
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use rusqlite::Connection;
 
+use super::varg;
+use super::card::{Face, Timestamp};
+use super::card::config::CardConfig;
+use super::control::content;
 use super::file;
 use super::model;
 use super::model::cardfolder;
 use super::model::carddb;
+use super::model::commit;
 use super::hash;
 
-pub fn zkcard(timeline_file: &PathBuf) {
-    let mut timeline: Connection = model::open_timeline(&timeline_file).unwrap();
+enum Placement {
+    Major,
+    Under(Face),
+    After(Face),
+}
+
+fn parse_arguments(args: &[String], config: &CardConfig) -> Result<Placement, String> {
+    match args {
+        [] => Ok(Placement::Major),
+        [option, name] if option == "--under" || option == "--after" => {
+            let face = config.card(name).ok_or_else(|| format!("Not a card name: {}", name))?;
+            if option == "--under" {
+                Ok(Placement::Under(face))
+            } else {
+                Ok(Placement::After(face))
+            }
+        },
+        _ => Err(String::from("Invalid arguments. Use --under <card> or --after <card>")),
+    }
+}
+
+fn next_card(timeline: &Connection, opencards: &cardfolder::CardFolder, placement: &Placement) -> Result<Face, String> {
+    let (card, first) = match placement {
+        Placement::Major => return cardfolder::next_major_card(opencards, timeline),
+        Placement::Under(card) => (card, card.first_child()),
+        Placement::After(card) => (card, card.next_sibling()),
+    };
+
+    let existing: HashSet<String> = content::existing_cards(&opencards.folder, timeline).iter()
+        .map(|card| card.name())
+        .collect();
+    if !existing.contains(&card.name()) {
+        return Err(format!("The card {} does not exist", card.name()));
+    }
+    let mut taken = carddb::timeline_card_names(timeline)?;
+    taken.extend(existing);
+    return Ok(cardfolder::next_free_sibling(first, &taken));
+}
+
+const RESERVATION_SECONDS: u64 = 24 * 60 * 60;
+
+/// Pick the next card and reserve its name. Another zk card may reserve the
+/// same name in between. Then pick again.
+fn reserve_next_card(timeline: &Connection, opencards: &cardfolder::CardFolder, placement: &Placement) -> Result<Face, String> {
+    let expired = SystemTime::now() - Duration::from_secs(RESERVATION_SECONDS);
+    carddb::release_stale_reservations(timeline, &Timestamp::from_system_time(expired))?;
+    for _ in 0..10 {
+        let next = next_card(timeline, opencards, placement)?;
+        if carddb::reserve_card(timeline, &next)? {
+            return Ok(next);
+        }
+    }
+    return Err(String::from("Fail to reserve a name for the card"));
+}
+
+pub fn zkcard(timeline_file: &Path, args: &varg::Args) {
+    let mut timeline: Connection = model::open_timeline(timeline_file).unwrap();
     let opencards = cardfolder::CardFolder::from_timeline(&timeline);
-    let cards = opencards.cards();
-    let next = cardfolder::next_available(&cards);
-    let next_location: PathBuf = next.location_in(&opencards.folder);
+    let placement = parse_arguments(&args.args, &opencards.config);
+    if let Err(msg) = placement {
+        eprintln!("{}", msg);
+        return;
+    }
+
+    let next = reserve_next_card(&timeline, &opencards, &placement.unwrap());
+    if let Err(msg) = next {
+        eprintln!("{}", msg);
+        return;
+    }
+    let next = next.unwrap();
+    let next_location: PathBuf = next.location_in(&opencards.folder, &opencards.config);
+
+    // Note(wistrandj): A file may be in the way if it is ignored or in
+    // another subfolder of the layout. Never write over it.
+    if next_location.exists() {
+        eprintln!("The file {} exists. Not creating the card {}", next_location.to_string_lossy(), next.name());
+        let _ = carddb::release_card(&timeline, &next);
+        return;
+    }
     eprintln!("Open a new card in {}", next.name());
+    // The subfolder of the card in the folder layout may not exist yet.
+    if let Err(msg) = std::fs::create_dir_all(next_location.parent().unwrap()) {
+        eprintln!("Fail to create the folder of the card. Reason: {}", msg);
+        let _ = carddb::release_card(&timeline, &next);
+        return;
+    }
     file::make_template(&next_location);
     file::edit(&next_location);
-    let hash: hash::Hash = model::blob::save(&timeline, &next_location);
-    carddb::save_card_and_hash(&mut timeline, &next, &hash);
+
+    let savepoint = timeline.savepoint().unwrap();
+    let message = format!("Create card {}", next.name());
+    let commit_id = commit::create_commit(&savepoint, &message, &commit::author());
+    if let Ok(commit_id) = commit_id {
+        let hash: Result<hash::Hash, &'static str> = model::blob::save(&savepoint, &next_location);
+        if let Ok(hash) = hash {
+            match carddb::create_card_and_hash(&savepoint, &next, &hash, commit_id) {
+                Ok(()) => {
+                    savepoint.commit().unwrap();
+                    return;
+                },
+                Err(msg) => eprintln!("{}", msg),
+            }
+        }
+    }
+    // The name stays reserved. `zk commit` saves the card and releases it.
+    eprintln!("Fail to save the card {} to the timeline", next.name());
 }
 
@@ -0,0 +1,93 @@
+// Explain why a file in the card folder is or is not a card. Usage:
+//   $ zk -t ./timeline.zk check-ignore .123a.swp README
+//   $ zk -t ./timeline.zk check-ignore ~/cards/0-999/123a
+//
+// The paths are relative to the card folder unless they exist as given.
+// The rules are in the .zkignore file of the card folder. Exit with 0 if
+// some path is ignored, 1 if none is and 2 on errors like git check-ignore.
+
+use std::path::{Path, PathBuf};
+
+use super::varg;
+use super::model;
+use super::model::cardfolder::{self, CardFolder};
+use super::model::ignore::{self, IgnoreRules, Reason};
+
+/// The path relative to the card folder with slashes.
+fn relative_path(folder: &Path, arg: &str) -> Result<String, String> {
+    let path = PathBuf::from(arg);
+    let relative = if path.exists() {
+        let path = path.canonicalize().map_err(|_| format!("Fail to read {}", arg))?;
+        let folder = folder.canonicalize().map_err(|_| String::from("Fail to read the card folder"))?;
+        match path.strip_prefix(&folder) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => return Err(format!("{} is outside of the card folder", arg)),
+        }
+    } else {
+        path
+    };
+
+    let parts: Vec<String> = relative.components().map(|part| part.as_os_str().to_string_lossy().to_string()).collect();
+    if parts.is_empty() || parts.iter().any(|part| part == ".." || part == "/") {
+        return Err(format!("{} is not a file in the card folder", arg));
+    }
+    return Ok(parts.join("/"));
+}
+
+/// What the file is when it is not ignored.
+fn describe_file(opencards: &CardFolder, relative: &str) -> String {
+    let (folder, config) = (&opencards.folder, &opencards.config);
+    let file_name = relative.rsplit('/').next().unwrap();
+    if folder.join(relative).is_dir() {
+        if !relative.contains('/') && config.layout.is_subfolder(relative) {
+            return String::from("a subfolder of the folder layout");
+        }
+        return String::from("a folder that is not scanned for cards");
+    }
+    if let Some(card) = config.card_of_file(relative) {
+        if !folder.join(relative).exists() {
+            return format!("no such file but it would be the card {}", card.name());
+        }
+        return format!("the card {}", card.name());
+    }
+    match config.card_of_file_name(file_name) {
+        Some(card) => format!("not a card: {} belongs in {}", card.name(), card.location_in(Path::new(""), config).to_string_lossy()),
+        None => String::from("not a card: the name does not match the file pattern"),
+    }
+}
+
+pub fn zkcheckignore(timeline_file: &Path, args: &varg::Args) -> i32 {
+    if args.args.is_empty() {
+        eprintln!("Give the paths to check");
+        return 2;
+    }
+
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let opencards = cardfolder::CardFolder::from_timeline(&timeline);
+    let rules = IgnoreRules::load(&opencards.folder);
+
+    let mut any_ignored = false;
+    for arg in &args.args {
+        let relative = match relative_path(&opencards.folder, arg) {
+            Ok(relative) => relative,
+            Err(msg) => { eprintln!("{}", msg); return 2; },
+        };
+
+        let is_dir = opencards.folder.join(&relative).is_dir();
+        let reason = rules.explain(&relative, is_dir);
+        any_ignored |= reason.is_ignored();
+        match reason {
+            Reason::IgnoreFile => println!("{}: ignored, it holds the ignore rules", relative),
+            Reason::Rule { rule, path } if !rule.negated => {
+                let folder = if path != relative { format!(" (the folder {})", path) } else { String::new() };
+                println!("{}: ignored by {}:{}: {}{}", relative, ignore::IGNORE_FILE, rule.line, rule.text, folder);
+            },
+            Reason::Rule { rule, .. } => {
+                println!("{}: not ignored by {}:{}: {}, {}", relative, ignore::IGNORE_FILE, rule.line, rule.text,
+                    describe_file(&opencards, &relative));
+            },
+            Reason::NoRule => println!("{}: no ignore rule, {}", relative, describe_file(&opencards, &relative)),
+        }
+    }
+    return if any_ignored { 0 } else { 1 };
+}
@@ -0,0 +1,49 @@
+// Save every new or modified card of the card folder to the timeline. Usage:
+//   $ zk -t ./timeline.zk commit
+//   $ zk -t ./timeline.zk commit -m "Notes from the meeting"
+
+use std::path::Path;
+
+use super::varg;
+use super::model;
+use super::model::cardfolder;
+use super::model::commit;
+use super::control::commit::CommitModifiedCards;
+
+pub fn zkcommit(timeline_file: &Path, args: &varg::Args) {
+    let mut message = String::new();
+    let mut arguments = args.args.iter();
+
+    while let Some(arg) = arguments.next() {
+        if arg == "-m" || arg == "--message" {
+            if let Some(value) = arguments.next() {
+                message = String::from(value);
+            } else {
+                eprintln!("Missing commit message");
+                return;
+            }
+        } else {
+            eprintln!("Invalid commit argument: {}", arg);
+            return;
+        }
+    }
+
+    let mut timeline = model::open_timeline(timeline_file).unwrap();
+    let opencards = cardfolder::CardFolder::from_timeline(&timeline);
+    let author = commit::author();
+
+    let cmd = CommitModifiedCards::new(&mut timeline, &opencards.folder, &message, &author);
+    let names: Vec<String> = cmd.cards().iter().map(|card| card.name()).collect();
+
+    match cmd.call_once() {
+        Ok(commit_id) => {
+            println!("Commit {} by {}", commit_id, author);
+            for name in names {
+                println!("  {}", name);
+            }
+        },
+        Err(msg) => {
+            eprintln!("{}", msg);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+// Show the changes of a card as a unified diff. Usage:
+//   $ zk -t ./timeline.zk diff 123                 # latest saved content vs the card folder
+//   $ zk -t ./timeline.zk diff 123 --rev 3         # revision 3 vs the card folder
+//   $ zk -t ./timeline.zk diff 123 --rev 3 --rev 5 # revision 3 vs revision 5
+//   $ zk -t ./timeline.zk diff 123 --hash 936a     # the blob 936a... vs the card folder
+//
+// The output is colored when written to a terminal. Use --color or
+// --no-color to choose. Exit status is 0 without differences, 1 with
+// differences and 2 on errors, like diff(1).
+
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+use rusqlite::Connection;
+
+use super::varg;
+use super::card;
+use super::card::config::CardConfig;
+use super::diff;
+use super::model;
+use super::model::{blob, carddb, cardfolder};
+use super::control::content;
+use super::control::content::Version;
+
+struct Arguments {
+    card: card::Face,
+    versions: Vec<Version>,
+    color: bool,
+}
+
+/// One side of the diff: a label and the content.
+struct Side {
+    label: String,
+    content: Vec<u8>,
+}
+
+fn parse_arguments(args: &varg::Args, config: &CardConfig) -> Result<Arguments, String> {
+    let mut card = None;
+    let mut versions = Vec::new();
+    let mut color = std::io::stdout().is_terminal();
+
+    let mut iter = args.args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--rev" || arg == "-r" {
+            let number = iter.next().map(|value| value.parse::<usize>());
+            if let Some(Ok(number)) = number {
+                versions.push(Version::Revision(number));
+            } else {
+                return Err(String::from("The --rev switch expects a revision number"));
+            }
+        } else if arg == "--hash" {
+            if let Some(prefix) = iter.next() {
+                versions.push(Version::Hash(String::from(prefix)));
+            } else {
+                return Err(String::from("The --hash switch expects a hash or a prefix of it"));
+            }
+        } else if arg == "--color" {
+            color = true;
+        } else if arg == "--no-color" {
+            color = false;
+        } else if card.is_none() {
+            card = config.card(arg);
+            if card.is_none() {
+                return Err(format!("Not a card name: {}", arg));
+            }
+        } else {
+            return Err(format!("Invalid diff argument: {}", arg));
+        }
+    }
+
+    if card.is_none() {
+        return Err(String::from("Give a card"));
+    }
+    if versions.len() > 2 {
+        return Err(String::from("Give at most two revisions or hashes"));
+    }
+
+    return Ok(Arguments { card: card.unwrap(), versions, color });
+}
+
+fn stored_side(conn: &Connection, card: &card::Face, version: &Version) -> Result<Side, String> {
+    let hash = content::stored_hash(conn, card, version)?;
+    let hash_str = hash.to_string();
+    let label = match version {
+        Version::Latest => format!("a/{}", card.name()),
+        _ => format!("a/{}\t{}", card.name(), version.describe()),
+    };
+    match blob::load(conn, hash) {
+        Some(content) => Ok(Side { label, content }),
+        None => Err(format!("{}: the content {} is missing from the timeline", card.name(), hash_str)),
+    }
+}
+
+fn open_side(conn: &Connection, card: &card::Face) -> Side {
+    let opencards = cardfolder::CardFolder::from_timeline(conn);
+    let file = card.location_in(&opencards.folder, &opencards.config);
+    match fs::read(&file) {
+        Ok(content) => Side { label: format!("b/{}", card.name()), content },
+        Err(_) => Side { label: String::from("/dev/null"), content: Vec::new() },
+    }
+}
+
+fn sides(conn: &Connection, arguments: &Arguments) -> Result<(Side, Side), String> {
+    let card = &arguments.card;
+    match arguments.versions.as_slice() {
+        [] => {
+            let old = if carddb::card_hash(conn, card).is_some() {
+                stored_side(conn, card, &Version::Latest)?
+            } else {
+                Side { label: String::from("/dev/null"), content: Vec::new() }
+            };
+            Ok((old, open_side(conn, card)))
+        },
+        [version] => Ok((stored_side(conn, card, version)?, open_side(conn, card))),
+        [old_version, new_version] => {
+            let old = stored_side(conn, card, old_version)?;
+            let mut new = stored_side(conn, card, new_version)?;
+            new.label = new.label.replacen("a/", "b/", 1);
+            Ok((old, new))
+        },
+        _ => Err(String::from("Give at most two revisions or hashes")),
+    }
+}
+
+pub fn zkdiff(timeline_file: &Path, args: &varg::Args) -> i32 {
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let arguments = parse_arguments(args, &carddb::card_config(&timeline));
+    if let Err(msg) = arguments {
+        eprintln!("{}", msg);
+        return 2;
+    }
+    let arguments = arguments.unwrap();
+
+    let sides = sides(&timeline, &arguments);
+    if let Err(msg) = sides {
+        eprintln!("{}", msg);
+        return 2;
+    }
+    let (old, new) = sides.unwrap();
+
+    if old.content.contains(&0) || new.content.contains(&0) {
+        if old.content == new.content {
+            return 0;
+        }
+        println!("Binary card {} differs", arguments.card.name());
+        return 1;
+    }
+
+    let old_text = String::from_utf8_lossy(&old.content);
+    let new_text = String::from_utf8_lossy(&new.content);
+    match diff::unified(&old.label, &new.label, &old_text, &new_text, arguments.color) {
+        Some(patch) => {
+            print!("{}", patch);
+            1
+        },
+        None => 0,
+    }
+}
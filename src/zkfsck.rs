@@ -0,0 +1,64 @@
+// Verify the integrity of the timeline and the card folder. Usage:
+//   $ zk -t ./timeline.zk fsck
+//   $ zk -t ./timeline.zk fsck --repair
+//
+// The repair only applies safe fixes: it deletes extra or corrupted copies
+// of content which has a valid copy, and stores content missing from the
+// timeline when the open card still has it. Exit status is 0 when nothing
+// is wrong, 1 when problems remain and 2 on errors.
+
+use std::path::Path;
+
+use super::varg;
+use super::model;
+use super::model::cardfolder;
+use super::control::fsck;
+
+pub fn zkfsck(timeline_file: &Path, args: &varg::Args) -> i32 {
+    let mut repair = false;
+    for arg in args.args.iter() {
+        if arg == "--repair" {
+            repair = true;
+        } else {
+            eprintln!("Invalid fsck argument: {}", arg);
+            return 2;
+        }
+    }
+
+    let mut timeline = model::open_timeline(timeline_file).unwrap();
+    let opencards = cardfolder::CardFolder::from_timeline(&timeline);
+
+    let findings = fsck::check(&timeline, &opencards.folder);
+    if let Err(msg) = findings {
+        eprintln!("{}", msg);
+        return 2;
+    }
+    let findings = findings.unwrap();
+
+    for finding in &findings {
+        let note = if finding.fix.is_some() { " (repairable)" } else { "" };
+        println!("{}{}", finding.problem.describe(), note);
+    }
+
+    if findings.len() == 0 {
+        println!("No problems found");
+        return 0;
+    }
+
+    let repairable = findings.iter().filter(|finding| finding.fix.is_some()).count();
+    if !repair {
+        println!("{} problems found, {} of them can be repaired with --repair", findings.len(), repairable);
+        return 1;
+    }
+
+    match fsck::repair(&mut timeline, &findings) {
+        Ok(count) => {
+            println!("{} problems found, {} repaired", findings.len(), count);
+            if count == findings.len() { 0 } else { 1 }
+        },
+        Err(msg) => {
+            eprintln!("{}", msg);
+            2
+        }
+    }
+}
@@ -0,0 +1,72 @@
+// Delete blobs that no card, revision or other table refers to. Usage:
+//   $ zk -t ./timeline.zk gc --dry-run
+//   $ zk -t ./timeline.zk gc --grace-days 14 --vacuum
+//
+// With --grace-days only blobs stored more than that many days ago are
+// deleted. Blobs stored before the timeline recorded times count as old.
+// The --vacuum switch shrinks the timeline file afterwards.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::varg;
+use super::model;
+use super::card::Timestamp;
+use super::control::gc::CollectGarbage;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+pub fn zkgc(timeline_file: &Path, args: &varg::Args) {
+    let mut dry_run = false;
+    let mut vacuum = false;
+    let mut stored_before: Option<Timestamp> = None;
+
+    let mut iter = args.args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--dry-run" || arg == "-n" {
+            dry_run = true;
+        } else if arg == "--vacuum" {
+            vacuum = true;
+        } else if arg == "--grace-days" {
+            let days = iter.next().and_then(|value| value.parse::<u64>().ok());
+            let time = days
+                .and_then(|days| days.checked_mul(SECONDS_PER_DAY))
+                .and_then(|seconds| SystemTime::now().checked_sub(Duration::from_secs(seconds)));
+            if let Some(time) = time {
+                // No blob is stored before 1970. The timestamps do not go
+                // much further back either.
+                stored_before = Some(Timestamp::from_system_time(time.max(UNIX_EPOCH)));
+            } else {
+                eprintln!("The --grace-days switch expects a number of days");
+                return;
+            }
+        } else {
+            eprintln!("Invalid gc argument: {}", arg);
+            return;
+        }
+    }
+
+    let mut timeline = model::open_timeline(timeline_file).unwrap();
+    let cmd = CollectGarbage::new(&mut timeline, stored_before.as_ref());
+    if let Err(msg) = cmd {
+        eprintln!("{}", msg);
+        return;
+    }
+    let cmd = cmd.unwrap();
+
+    for info in cmd.blobs() {
+        println!("{} {}", info.content_sha256, info.size);
+    }
+    let count = cmd.blobs().len();
+    let bytes = cmd.reclaimed_bytes();
+
+    if dry_run {
+        println!("Would delete {} blobs and reclaim {} bytes", count, bytes);
+        return;
+    }
+
+    match cmd.call_once(vacuum) {
+        Ok(_) => println!("Deleted {} blobs and reclaimed {} bytes", count, bytes),
+        Err(msg) => eprintln!("{}", msg),
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use rusqlite::{params};
 use super::varg;
 
@@ -27,7 +27,7 @@ fn parse_arguments(args: &varg::Args) -> Option<Arguments> {
             return None;
         }
 
-        let first_argument = arguments.get(0).unwrap();
+        let first_argument = arguments.first().unwrap();
 
         if arguments.len() == 1 {
             let timeline_file_location = String::from(first_argument);
@@ -67,7 +67,7 @@ fn parse_arguments(args: &varg::Args) -> Option<Arguments> {
     return None;
 }
 
-pub fn zkinit(timeline_file: &PathBuf, args: &varg::Args) {
+pub fn zkinit(_timeline_file: &Path, args: &varg::Args) {
     let args_opt = parse_arguments(args);
     if args_opt.is_none() {
         // eprintln!("");
@@ -0,0 +1,58 @@
+// Show the revision history of a card, newest first. Usage:
+//   $ zk -t ./timeline.zk log 123a
+
+use std::path::Path;
+
+use super::varg;
+use super::card::Timestamp;
+use super::model;
+use super::model::carddb;
+use super::model::revision;
+
+pub fn zklog(timeline_file: &Path, args: &varg::Args) {
+    if args.args.len() != 1 {
+        eprintln!("Give exactly one card");
+        return;
+    }
+
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let card_name = args.args.first().unwrap();
+    let face = carddb::card_config(&timeline).card(card_name);
+    if face.is_none() {
+        eprintln!("Not a card name: {}", card_name);
+        return;
+    }
+    let face = face.unwrap();
+
+    let revisions = revision::revisions_of_card(&timeline, &face);
+
+    match revisions {
+        Ok(revisions) => {
+            if revisions.len() == 0 {
+                eprintln!("No revisions of card {}", face.name());
+                return;
+            }
+            for rev in revisions {
+                let time = if rev.revision_time == Timestamp::unknown() {
+                    String::from("-")
+                } else {
+                    rev.revision_time.to_string()
+                };
+                let author = rev.author.unwrap_or_else(|| String::from("-"));
+                println!("revision {} {}", rev.number, rev.content_sha256);
+                println!("Author: {}", author);
+                println!("Date:   {}", time);
+                if let Some(message) = rev.commit_message {
+                    if message.len() > 0 {
+                        println!();
+                        println!("    {}", message);
+                    }
+                }
+                println!();
+            }
+        },
+        Err(msg) => {
+            eprintln!("{}", msg);
+        }
+    }
+}
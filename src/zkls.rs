@@ -0,0 +1,119 @@
+// List cards with their create and modify times. Usage:
+//   $ zk -t ./timeline.zk ls
+//   $ zk -t ./timeline.zk ls --open
+//   $ zk -t ./timeline.zk ls --modified-since 2026-09-01 --sort modified
+//
+// Without --open the cards and times come from the timeline. With --open
+// they come from the files in the card folder. The "since" limits are
+// inclusive and the "before" limits are exclusive. Dates are in UTC.
+
+use std::path::Path;
+
+use super::varg;
+use super::model;
+use super::model::cardfolder;
+use super::card::{Meta, Timestamp};
+use super::control::content;
+
+struct Arguments {
+    open: bool,
+    sort: String,
+    modified_since: Option<Timestamp>,
+    modified_before: Option<Timestamp>,
+    created_since: Option<Timestamp>,
+    created_before: Option<Timestamp>,
+}
+
+fn parse_arguments(args: &varg::Args) -> Result<Arguments, String> {
+    let mut arguments = Arguments {
+        open: false,
+        sort: String::from("name"),
+        modified_since: None,
+        modified_before: None,
+        created_since: None,
+        created_before: None,
+    };
+
+    let mut iter = args.args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--open" {
+            arguments.open = true;
+            continue;
+        }
+
+        let value = iter.next();
+        if value.is_none() {
+            return Err(format!("Missing value for {}", arg));
+        }
+        let value = value.unwrap();
+
+        if arg == "--sort" {
+            if !["name", "modified", "created"].contains(&value.as_str()) {
+                return Err(format!("Invalid sort order {}. Use name, modified or created", value));
+            }
+            arguments.sort = String::from(value);
+            continue;
+        }
+
+        let time = Timestamp::parse(value);
+        if time.is_none() {
+            return Err(format!("Invalid date or time: {}", value));
+        }
+
+        match arg.as_str() {
+            "--modified-since" => arguments.modified_since = time,
+            "--modified-before" => arguments.modified_before = time,
+            "--created-since" => arguments.created_since = time,
+            "--created-before" => arguments.created_before = time,
+            _ => return Err(format!("Invalid ls argument: {}", arg)),
+        }
+    }
+
+    return Ok(arguments);
+}
+
+fn in_range(time: &Timestamp, since: &Option<Timestamp>, before: &Option<Timestamp>) -> bool {
+    if let Some(since) = since {
+        if time < since {
+            return false;
+        }
+    }
+    if let Some(before) = before {
+        if time >= before {
+            return false;
+        }
+    }
+    return true;
+}
+
+pub fn zkls(timeline_file: &Path, args: &varg::Args) {
+    let arguments = parse_arguments(args);
+    if let Err(msg) = arguments {
+        eprintln!("{}", msg);
+        return;
+    }
+    let arguments = arguments.unwrap();
+
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let cards: Vec<Meta> = if arguments.open {
+        let opencards = cardfolder::CardFolder::from_timeline(&timeline);
+        content::all_open_cards(&opencards, model::carddb::hash_algorithm(&timeline))
+    } else {
+        content::all_timeline_cards(&timeline)
+    };
+
+    let mut cards: Vec<Meta> = cards.into_iter()
+        .filter(|card| in_range(&card.modify_time, &arguments.modified_since, &arguments.modified_before))
+        .filter(|card| in_range(&card.create_time, &arguments.created_since, &arguments.created_before))
+        .collect();
+
+    match arguments.sort.as_str() {
+        "modified" => cards.sort_by(|a, b| a.modify_time.cmp(&b.modify_time).then(a.face.cmp(&b.face))),
+        "created" => cards.sort_by(|a, b| a.create_time.cmp(&b.create_time).then(a.face.cmp(&b.face))),
+        _ => cards.sort_by(|a, b| a.face.cmp(&b.face)),
+    }
+
+    for card in cards {
+        println!("{}\t{}\t{}", card.name(), card.create_time, card.modify_time);
+    }
+}
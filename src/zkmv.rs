@@ -0,0 +1,66 @@
+// Move a card and the cards below it to another name. Usage:
+//   $ zk -t ./timeline.zk mv 123a 45c
+//   $ zk -t ./timeline.zk mv 123a 45c --dry-run
+//
+// 123a becomes 45c, 123a1 becomes 45c1 and so on, in the card folder and in
+// the timeline including the history. The links to the moved cards in the
+// text of the open cards, like [[123a1]], are rewritten. A bare 123a1 is not
+// a link. The dry run shows every rewritten line. The rewritten cards show up
+// as modified to be committed. Tags move only when a major card moves to
+// another major card. A major card with tags cannot move below another card.
+
+use std::path::Path;
+
+use super::varg;
+use super::model;
+use super::model::cardfolder;
+use super::control::rename::MoveCards;
+
+pub fn zkmv(timeline_file: &Path, args: &varg::Args) {
+    let dry_run = args.args.iter().any(|arg| arg == "--dry-run" || arg == "-n");
+    let names: Vec<&String> = args.args.iter().filter(|arg| !arg.starts_with('-')).collect();
+    if names.len() != 2 || names.len() + (dry_run as usize) != args.args.len() {
+        eprintln!("Give the card to move and its new name");
+        return;
+    }
+
+    let mut timeline = model::open_timeline(timeline_file).unwrap();
+    let opencards = cardfolder::CardFolder::from_timeline(&timeline);
+    let mut faces = Vec::new();
+    for name in names {
+        match opencards.config.card(name) {
+            Some(face) => faces.push(face),
+            None => {
+                eprintln!("Not a card name: {}", name);
+                return;
+            }
+        }
+    }
+
+    let command = MoveCards::new(&mut timeline, &opencards.folder, &faces[0], &faces[1]);
+    if let Err(msg) = command {
+        eprintln!("{}", msg);
+        return;
+    }
+    let command = command.unwrap();
+
+    for (old, new) in command.renames() {
+        println!("{} -> {}", old.name(), new.name());
+    }
+    for rewrite in command.rewrites() {
+        println!("rewrite {} references in {}", rewrite.references, rewrite.card.name());
+        if dry_run {
+            for (number, old, new) in &rewrite.lines {
+                println!("  {}:{}: - {}", rewrite.card.name(), number, old);
+                println!("  {}:{}: + {}", rewrite.card.name(), number, new);
+            }
+        }
+    }
+    if dry_run {
+        return;
+    }
+
+    if let Err(msg) = command.call_once() {
+        eprintln!("{}", msg);
+    }
+}
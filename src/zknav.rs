@@ -0,0 +1,85 @@
+// Move through the trains of cards. Usage:
+//   $ zk -t ./timeline.zk parent 123a1
+//   $ zk -t ./timeline.zk children 123a
+//   $ zk -t ./timeline.zk next 123a
+//
+// The answers are cards that exist in the card folder or in the timeline.
+// Next is the closest following card on the same level, 123c if there is no
+// 123b. The exit status is 1 if there is no such card and 2 on an error.
+
+use std::path::Path;
+
+use super::varg;
+use super::card::Face;
+use super::control::content;
+use super::model;
+use super::model::cardfolder;
+
+fn card_and_existing(timeline_file: &Path, args: &varg::Args) -> Result<(Face, Vec<Face>), String> {
+    let card_name = match &args.args[..] {
+        [name] => name,
+        _ => return Err(String::from("Give one card")),
+    };
+
+    let timeline = model::open_timeline(timeline_file).ok_or_else(|| String::from("Fail to open the timeline"))?;
+    let opencards = cardfolder::CardFolder::from_timeline(&timeline);
+    let face = opencards.config.card(card_name).ok_or_else(|| format!("Not a card name: {}", card_name))?;
+    let existing = content::existing_cards(&opencards.folder, &timeline);
+    if !existing.contains(&face) {
+        return Err(format!("The card {} does not exist", face.name()));
+    }
+    return Ok((face, existing));
+}
+
+fn print_found(found: Option<Face>) -> i32 {
+    match found {
+        Some(card) => { println!("{}", card.name()); 0 },
+        None => 1,
+    }
+}
+
+pub fn zkparent(timeline_file: &Path, args: &varg::Args) -> i32 {
+    let (face, existing) = match card_and_existing(timeline_file, args) {
+        Ok(found) => found,
+        Err(msg) => { eprintln!("{}", msg); return 2; },
+    };
+
+    // The parent may be deleted. Then the closest ancestor that exists.
+    let mut parent = face.parent();
+    while let Some(card) = &parent {
+        if existing.contains(card) {
+            break;
+        }
+        parent = card.parent();
+    }
+    return print_found(parent);
+}
+
+pub fn zkchildren(timeline_file: &Path, args: &varg::Args) -> i32 {
+    let (face, existing) = match card_and_existing(timeline_file, args) {
+        Ok(found) => found,
+        Err(msg) => { eprintln!("{}", msg); return 2; },
+    };
+
+    let mut children: Vec<&Face> = existing.iter()
+        .filter(|card| card.parent().as_ref() == Some(&face))
+        .collect();
+    children.sort();
+    for card in &children {
+        println!("{}", card.name());
+    }
+    return if children.len() > 0 { 0 } else { 1 };
+}
+
+pub fn zknext(timeline_file: &Path, args: &varg::Args) -> i32 {
+    let (face, existing) = match card_and_existing(timeline_file, args) {
+        Ok(found) => found,
+        Err(msg) => { eprintln!("{}", msg); return 2; },
+    };
+
+    let next = existing.into_iter()
+        .filter(|card| card.parent().map(|parent| parent.name()) == face.parent().map(|parent| parent.name()))
+        .filter(|card| card.sibling_index() > face.sibling_index())
+        .min_by_key(|card| card.sibling_index());
+    return print_found(next);
+}
@@ -0,0 +1,52 @@
+// Move the card files to another folder layout. Usage:
+//   $ zk -t ./timeline.zk relayout ranges:1000
+//   $ zk -t ./timeline.zk relayout flat --dry-run
+//
+// The ranges layout spreads the cards to subfolders by their major numbers
+// like 0-999/123a. The attachment folders move with the cards. Files that
+// are not cards stay where they are.
+
+use std::path::{Path, PathBuf};
+
+use super::varg;
+use super::card::layout::FolderLayout;
+use super::model;
+use super::model::cardfolder;
+use super::control::relayout::Relayout;
+
+pub fn zkrelayout(timeline_file: &Path, args: &varg::Args) {
+    let dry_run = args.args.iter().any(|arg| arg == "--dry-run" || arg == "-n");
+    let names: Vec<&String> = args.args.iter().filter(|arg| !arg.starts_with('-')).collect();
+    if names.len() != 1 || names.len() + (dry_run as usize) != args.args.len() {
+        eprintln!("Give the folder layout: flat or ranges:<size>");
+        return;
+    }
+    let layout = FolderLayout::from_name(Some(names[0]));
+    if let Err(msg) = layout {
+        eprintln!("{}", msg);
+        return;
+    }
+    let layout = layout.unwrap();
+
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let opencards = cardfolder::CardFolder::from_timeline(&timeline);
+    let command = Relayout::new(&timeline, &opencards.folder, layout);
+    if let Err(msg) = command {
+        eprintln!("{}", msg);
+        return;
+    }
+    let command = command.unwrap();
+
+    if dry_run {
+        for (from, to) in command.moves() {
+            let relative = |path: &PathBuf| path.strip_prefix(&opencards.folder).unwrap_or(path).to_string_lossy().to_string();
+            println!("{} -> {}", relative(from), relative(to));
+        }
+        return;
+    }
+
+    match command.call_once() {
+        Ok(count) => println!("Moved {} files and folders to the {} layout", count, layout.label()),
+        Err(msg) => eprintln!("{}", msg),
+    }
+}
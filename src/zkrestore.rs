@@ -0,0 +1,90 @@
+// Write cards from the timeline back to the filesystem. Usage:
+//   $ zk -t ./timeline.zk restore 123a
+//   $ zk -t ./timeline.zk restore 123a --rev 2 --force
+//   $ zk -t ./timeline.zk restore 123a --hash 936a
+//   $ zk -t ./timeline.zk checkout ./cards
+//
+// Restore writes a card into the card folder. Checkout writes the latest
+// content of every card into an empty folder, e.g. on a new machine.
+
+use std::path::{Path, PathBuf};
+
+use super::varg;
+use super::model;
+use super::model::cardfolder;
+use super::control::content::Version;
+use super::control::restore::{Checkout, RestoreCard};
+
+pub fn zkrestore(timeline_file: &Path, args: &varg::Args) {
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let opencards = cardfolder::CardFolder::from_timeline(&timeline);
+
+    let mut card = None;
+    let mut version = Version::Latest;
+    let mut force = false;
+
+    let mut iter = args.args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--rev" || arg == "-r" {
+            let number = iter.next().map(|value| value.parse::<usize>());
+            if let Some(Ok(number)) = number {
+                version = Version::Revision(number);
+            } else {
+                eprintln!("The --rev switch expects a revision number");
+                return;
+            }
+        } else if arg == "--hash" {
+            if let Some(prefix) = iter.next() {
+                version = Version::Hash(String::from(prefix));
+            } else {
+                eprintln!("The --hash switch expects a hash or a prefix of it");
+                return;
+            }
+        } else if arg == "--force" || arg == "-f" {
+            force = true;
+        } else if card.is_none() {
+            card = opencards.config.card(arg);
+            if card.is_none() {
+                eprintln!("Not a card name: {}", arg);
+                return;
+            }
+        } else {
+            eprintln!("Invalid restore argument: {}", arg);
+            return;
+        }
+    }
+
+    if card.is_none() {
+        eprintln!("Give a card to restore");
+        return;
+    }
+    let card = card.unwrap();
+
+    let result = RestoreCard::new(&timeline, &opencards.folder, &card, &version, force)
+        .and_then(|cmd| cmd.call_once());
+
+    match result {
+        Ok(_) => println!("Restored {}", card.location_in(&opencards.folder, &opencards.config).to_string_lossy()),
+        Err(msg) => eprintln!("{}", msg),
+    }
+}
+
+pub fn zkcheckout(timeline_file: &Path, args: &varg::Args) {
+    if args.args.len() != 1 {
+        eprintln!("Give exactly one folder to checkout the cards to");
+        return;
+    }
+    let folder = PathBuf::from(args.args.first().unwrap());
+
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let result = Checkout::new(&timeline, &folder)
+        .and_then(|cmd| cmd.call_once());
+
+    match result {
+        Ok(count) => {
+            println!("Wrote {} cards to {}", count, folder.to_string_lossy());
+            println!("Use 'zk set location {}' to make it the card folder", folder.to_string_lossy());
+        },
+        Err(msg) => eprintln!("{}", msg),
+    }
+}
@@ -1,19 +1,19 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use super::hash;
 use super::model;
+use super::card::naming::NamingScheme;
+use super::card::pattern::FilePattern;
+use super::control::content;
+use super::control::repattern::Repattern;
 
-#[derive(Debug)]
-struct A {
-    version: u32,
-    default_location: String
-}
-
-pub fn zkset(timeline_file: &PathBuf, args: &Vec<String>) {
+pub fn zkset(timeline_file: &Path, args: &[String]) {
     if args.len() < 2 {
         eprintln!("No setting or value given");
+        return;
     }
 
-    let setting = args.get(0).unwrap();
+    let setting = args.first().unwrap();
     let value = args.get(1).unwrap();
 
     if setting == "location" {
@@ -23,13 +23,110 @@ pub fn zkset(timeline_file: &PathBuf, args: &Vec<String>) {
             return;
         }
 
-        let timeline = model::open_timeline(&timeline_file).unwrap();
+        let timeline = model::open_timeline(timeline_file).unwrap();
         model::carddb::set_default_location(&timeline, &default_location);
 
         let default_location = model::carddb::default_location(&timeline).unwrap();
         let version = model::carddb::version(&timeline).unwrap();
         println!("Version: {}", version);
         println!("Location for open cards: {}", default_location.to_string_lossy());
+    } else if setting == "sidecar-threshold" {
+        // Blobs larger than this many bytes are stored in the objects
+        // directory next to the timeline file. "off" stores all in the timeline.
+        let threshold = if value == "off" {
+            None
+        } else if let Ok(bytes) = value.parse::<u64>() {
+            Some(bytes)
+        } else {
+            eprintln!("The sidecar threshold must be a number of bytes or off");
+            return;
+        };
+
+        let timeline = model::open_timeline(timeline_file).unwrap();
+        if let Err(msg) = model::carddb::set_sidecar_threshold(&timeline, threshold) {
+            eprintln!("{}", msg);
+            return;
+        }
+
+        match model::carddb::sidecar_threshold(&timeline) {
+            Some(bytes) => println!("Blobs larger than {} bytes are stored in the objects directory", bytes),
+            None => println!("All blobs are stored in the timeline file"),
+        }
+    } else if setting == "codec" {
+        // Codec of the new blobs: deflate or none. Convert the stored blobs
+        // with `zk blob recompress`.
+        let codec = model::codec::Codec::from_name(Some(value));
+        if let Err(msg) = codec {
+            eprintln!("{}", msg);
+            return;
+        }
+
+        let timeline = model::open_timeline(timeline_file).unwrap();
+        if let Err(msg) = model::carddb::set_codec(&timeline, codec.unwrap()) {
+            eprintln!("{}", msg);
+            return;
+        }
+        println!("New blobs are stored with codec {}", model::carddb::codec(&timeline).label());
+    } else if setting == "hash-algorithm" {
+        // Algorithm of the hashes of the new blobs: sha256 or blake3. The
+        // stored blobs keep their hashes. Hash them again with `zk blob rehash`.
+        let algorithm = hash::Algorithm::from_name(value);
+        if let Err(msg) = algorithm {
+            eprintln!("{}", msg);
+            return;
+        }
+
+        let timeline = model::open_timeline(timeline_file).unwrap();
+        if let Err(msg) = model::carddb::set_hash_algorithm(&timeline, algorithm.unwrap()) {
+            eprintln!("{}", msg);
+            return;
+        }
+        println!("New blobs are hashed with {}", model::carddb::hash_algorithm(&timeline).name());
+    } else if setting == "naming-scheme" {
+        // How the cards are named: alphanumeric, luhmann, timestamp or
+        // padded:<width>. The cards would not be renamed so the scheme can
+        // be changed only while there are no cards.
+        let scheme = NamingScheme::from_name(Some(value));
+        if let Err(msg) = scheme {
+            eprintln!("{}", msg);
+            return;
+        }
+
+        let timeline = model::open_timeline(timeline_file).unwrap();
+        let opencards = model::cardfolder::CardFolder::from_timeline(&timeline);
+        let has_cards = !content::all_timeline_cards(&timeline).is_empty()
+            || !opencards.cards().is_empty()
+            || !opencards.other_files().is_empty();
+        if has_cards {
+            eprintln!("The naming scheme cannot be changed when there are cards");
+            return;
+        }
+        if let Err(msg) = model::carddb::set_naming_scheme(&timeline, scheme.unwrap()) {
+            eprintln!("{}", msg);
+            return;
+        }
+        println!("Cards are named in the {} scheme", model::carddb::naming_scheme(&timeline).label());
+    } else if setting == "file-pattern" {
+        // How the card files are named like {name}.md or {name}-{slug}.md.
+        // The card files in the folder are renamed to the new pattern.
+        let pattern = FilePattern::from_name(Some(value));
+        if let Err(msg) = pattern {
+            eprintln!("{}", msg);
+            return;
+        }
+        let pattern = pattern.unwrap();
+
+        let timeline = model::open_timeline(timeline_file).unwrap();
+        let opencards = model::cardfolder::CardFolder::from_timeline(&timeline);
+        let result = Repattern::new(&timeline, &opencards, &pattern)
+            .and_then(|cmd| cmd.call_once());
+        if let Err(msg) = result {
+            eprintln!("{}", msg);
+            return;
+        }
+        println!("Card files are named as {}", model::carddb::file_pattern(&timeline));
+    } else {
+        eprintln!("Unknown setting {}", setting);
     }
 }
 
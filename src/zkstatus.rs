@@ -0,0 +1,60 @@
+// Show which cards in the card folder differ from the timeline. Usage:
+//   $ zk -t ./timeline.zk status
+//   $ zk -t ./timeline.zk status --all
+//   $ zk -t ./timeline.zk status --porcelain
+//
+// The porcelain output prints one card per line as "<code> <card> <sha256>"
+// where the code is one of N (new), M (modified), D (deleted) or U (unchanged).
+// The format is stable for scripts.
+
+use std::path::Path;
+
+use super::varg;
+use super::model;
+use super::model::cardfolder;
+use super::control::content;
+use super::control::content::Status;
+
+pub fn zkstatus(timeline_file: &Path, args: &varg::Args) {
+    let mut porcelain = false;
+    let mut show_all = false;
+
+    for arg in args.args.iter() {
+        match arg.as_str() {
+            "--porcelain" => { porcelain = true; },
+            "--all" | "-a" => { show_all = true; },
+            _ => {
+                eprintln!("Invalid status argument: {}", arg);
+                return;
+            }
+        }
+    }
+
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let opencards = cardfolder::CardFolder::from_timeline(&timeline);
+    let statuses = content::status(&opencards.folder, &timeline);
+
+    if porcelain {
+        for card in statuses {
+            println!("{} {} {}", card.status.code(), card.card.name(), card.card.content_sha256);
+        }
+        return;
+    }
+
+    let mut unchanged = 0;
+    for card in statuses.iter() {
+        if card.status == Status::Unchanged {
+            unchanged += 1;
+            if !show_all {
+                continue;
+            }
+        }
+        println!("{:>10}: {}", card.status.label(), card.card.name());
+    }
+
+    if unchanged == statuses.len() {
+        println!("All {} cards are unchanged", unchanged);
+    } else if !show_all {
+        println!("{} unchanged cards", unchanged);
+    }
+}
@@ -1,54 +1,62 @@
 use crate::varg::Args;
 use rusqlite::Connection;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use crate::control::tag as tag_lib;
-use crate::card;
 use crate::model;
+use crate::model::carddb;
+use crate::card::Face;
 
 fn set_tag_to_given_cards(args: &Args) -> Result<(), &'static str> {
     let mut iter = args.args.iter();
     let tag_name = iter.next().unwrap();
-    if !tag_lib::is_valid_tag(&tag_name) {
-        eprintln!("Invalid tag name: {}", tag_name);
+    if !tag_lib::is_valid_tag(tag_name) {
         return Err("Invalid tag name")
     }
     let cards_iter = iter;
 
     let timeline_file = args.timeline_file.as_ref().unwrap();
     let connection = model::open_timeline(timeline_file).unwrap();
+    let config = carddb::card_config(&connection);
 
-    let mut set_tag_commands: Vec<tag_lib::SetTag> = Vec::new();
+    let mut set_tag_commands: Vec<(Face, tag_lib::SetTag)> = Vec::new();
 
     for card_name in cards_iter {
         let card_path = PathBuf::from(card_name);
         let card_file_name = card_path.file_name().unwrap();
         let card_file_name: String = card_file_name.to_string_lossy().to_string();
-        let card_face = card::Face::from_name(&card_file_name);
+        let card_face = config.card(card_name).or_else(|| config.card_of_file_name(&card_file_name));
         if let Some(card_face) = card_face {
-            let maybe_set_tag = tag_lib::SetTag::new(&connection, tag_name, card_face)?;
+            let maybe_set_tag = tag_lib::SetTag::new(&connection, tag_name, card_face.clone())?;
             if let Some(set_tag) = maybe_set_tag {
-                set_tag_commands.push(set_tag);
+                set_tag_commands.push((card_face, set_tag));
             }
         } else {
-            eprintln!("{}", format!("Not a card name: {}", card_file_name));
+            eprintln!("Not a card name: {}", card_file_name);
         }
     }
 
-    // Assuming there is no faults, do the side-effect thing.
-
-    for cmd in set_tag_commands {
+    // Tag the rest of the cards even if one of them fails and report each failure.
+    let mut failed = false;
+    for (card_face, cmd) in set_tag_commands {
         // Todo(wistrandj): NIL argument.
-        cmd.call_once(());
+        if let Err(msg) = cmd.call_once(()) {
+            eprintln!("Fail to tag the card {}: {}", card_face.name(), msg);
+            failed = true;
+        }
     }
 
+    if failed {
+        return Err("Some of the cards were not tagged");
+    }
     Ok(())
 }
 
 fn delete_tag_of_given_cards(connection: &Connection, tag: &str, cards: &[String]) -> Result<(), &'static str> {
     let mut cmds = Vec::new();
+    let config = carddb::card_config(connection);
 
     for card in cards {
-        let card_face = card::Face::from_name(card.as_str());
+        let card_face = config.card(card.as_str());
         if let Some(card_face) = card_face {
             let delete_command = tag_lib::DeleteTag::new(connection, tag, card_face)?;
             cmds.push(delete_command);
@@ -57,10 +65,17 @@ fn delete_tag_of_given_cards(connection: &Connection, tag: &str, cards: &[String
         }
     }
 
-    for cmd in cmds {
-        cmd.call_once()?;
+    let mut failed = false;
+    for (card, cmd) in cards.iter().zip(cmds) {
+        if let Err(msg) = cmd.call_once() {
+            eprintln!("Fail to untag the card {}: {}", card, msg);
+            failed = true;
+        }
     }
 
+    if failed {
+        return Err("The tag was not deleted from some of the cards");
+    }
     Ok(())
 }
 
@@ -74,25 +89,25 @@ fn help_text() {
     println!("Usage of tag subcommand:");
     println!("Set the tag DCN1 to cards");
     println!("  zk -t ./here.zk tag DCN1 15 101");
-    println!("");
+    println!();
     println!("Show all tags");
     println!("   zk -t ./here.zk tag --list");
-    println!("");
+    println!();
     println!("Show tags of the given card");
     println!("   zk -t ./here.zk tag --list 101");
-    println!("");
+    println!();
     println!("Delete a tag of the given card");
     println!("   zk -t ./here.zk tag --delete DCN! 101");
 }
 
-pub fn zktag(timeline: &PathBuf, args: &Args) -> Result<(), &'static str> {
+pub fn zktag(_timeline: &Path, args: &Args) -> Result<(), &'static str> {
     // Todo(wistrandj): Feature to rename a tag
     let parameters = &args.args;
     if parameters.len() == 0 {
-        println!("Need more arguments");
+        return Err("Need more arguments");
     }
 
-    let first_argument = parameters.get(0).unwrap();
+    let first_argument = parameters.first().unwrap();
     if first_argument == "--list" || first_argument == "-l" {
         // Todo(wistrandj): This feature shows all tags. Add a feature to this switch: The user can
         // give a list of cards. Show only tags on those cards. User does not give any cards: show
@@ -103,9 +118,10 @@ pub fn zktag(timeline: &PathBuf, args: &Args) -> Result<(), &'static str> {
         if parameters.len() > 1 {  // The first argument is --list or -l
             // Show tags on the given cards
             let mut major_numbers = Vec::new();
+            let config = carddb::card_config(&connection);
 
             for card_name in &parameters[1..] {
-                let cardface = card::Face::from_name(card_name);
+                let cardface = config.card(card_name);
                 if let Some(cardface) = cardface {
                     let major_number = cardface.major_number();
                     major_numbers.push(major_number);
@@ -133,25 +149,17 @@ pub fn zktag(timeline: &PathBuf, args: &Args) -> Result<(), &'static str> {
         if let Some(tag_name) = parameters.get(1) {
             let cards_or_empty_list = &parameters.as_slice()[2..];
             if cards_or_empty_list.len() == 0 {
-                let success = delete_whole_tag(&connection, tag_name);
-                match success {
-                    Ok(_) => { return Ok(()) },
-                    Err(msg) => {
-                        eprintln!("{}", msg);
-                        return Err(msg);
-                    }
-                }
+                return delete_whole_tag(&connection, tag_name);
             } else {
                 return delete_tag_of_given_cards(&connection, tag_name, cards_or_empty_list);
             }
         } else {
-            eprintln!("Missing tag");
+            return Err("Missing tag");
         }
     } else if first_argument == "--show" || first_argument == "-s" {
         // Show all tags of given list of cards.
         let mut user_args_tags = Vec::new();
         if parameters.len() == 1 {   // The first argument is "--show" or "-s"
-            eprintln!("Missing tag name");
             return Err("Missing tag name")
         }
         for possibly_tag_argument in &parameters[1..] {
@@ -161,7 +169,6 @@ pub fn zktag(timeline: &PathBuf, args: &Args) -> Result<(), &'static str> {
             }
 
             if user_args_tags.len() == 0 {
-                eprintln!("No tags given");
                 return Err("No tags given");
             }
 
@@ -170,15 +177,9 @@ pub fn zktag(timeline: &PathBuf, args: &Args) -> Result<(), &'static str> {
         let connection = model::open_timeline(timeline_file).unwrap();
         let cmd = tag_lib::ShowAllCardsHavingTag::new(&connection, &user_args_tags);
 
-        let result = cmd.call_once();
-
-        if let Ok(major_card_numbers) = result {
-            for major_card in major_card_numbers {
-                println!("{}", major_card);
-            }
-        } else  if let Err(msg) = result{
-            eprintln!("{}", msg);
-            return Err(msg);
+        let major_card_numbers = cmd.call_once()?;
+        for major_card in major_card_numbers {
+            println!("{}", major_card);
         }
     } else if args.args.len() > 1 {
         // Set a tag to given cards
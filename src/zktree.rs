@@ -0,0 +1,123 @@
+// Show the cards as a tree by their names. Usage:
+//   $ zk -t ./timeline.zk tree
+//   $ zk -t ./timeline.zk tree 123a
+//   $ zk -t ./timeline.zk tree 123 --depth 1
+//
+// Each card is shown with its title, the first line of the card without the
+// leading #'s, and the number of cards below it in brackets. A card that does
+// not exist but has cards below it is shown as (missing). The cards exist in
+// the card folder or in the timeline. The depth counts the levels below the
+// root, or below the major cards if no root is given.
+
+use std::fs;
+use std::path::Path;
+use rusqlite::Connection;
+
+use super::varg;
+use super::card::Face;
+use super::card::config::CardConfig;
+use super::card::pattern::title_of;
+use super::card::tree::{self, Node};
+use super::control::content;
+use super::model;
+use super::model::{blob, carddb, cardfolder};
+use super::model::cardfolder::CardFolder;
+
+struct Arguments {
+    root: Option<Face>,
+    depth: Option<usize>,
+}
+
+fn parse_arguments(args: &varg::Args, config: &CardConfig) -> Result<Arguments, String> {
+    let mut arguments = Arguments { root: None, depth: None };
+
+    let mut iter = args.args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--depth" || arg == "-d" {
+            let depth = iter.next().ok_or_else(|| String::from("Missing depth"))?;
+            let depth = depth.parse::<usize>().map_err(|_| format!("Invalid depth: {}", depth))?;
+            arguments.depth = Some(depth);
+        } else if arguments.root.is_none() {
+            let root = config.card(arg).ok_or_else(|| format!("Not a card name: {}", arg))?;
+            arguments.root = Some(root);
+        } else {
+            return Err(format!("Invalid tree argument: {}", arg));
+        }
+    }
+    return Ok(arguments);
+}
+
+fn card_title(timeline: &Connection, opencards: &CardFolder, card: &Face) -> String {
+    let file = card.location_in(&opencards.folder, &opencards.config);
+    let content = if file.is_file() {
+        fs::read(&file).ok()
+    } else {
+        carddb::card_hash(timeline, card).and_then(|hash| blob::load(timeline, hash))
+    };
+    return content.map(|content| title_of(&String::from_utf8_lossy(&content))).unwrap_or_default();
+}
+
+struct Printer<'a> {
+    timeline: &'a Connection,
+    opencards: &'a CardFolder,
+    depth: Option<usize>,
+}
+
+impl<'a> Printer<'a> {
+    fn line(&self, node: &Node) -> String {
+        let title = if node.exists {
+            card_title(self.timeline, self.opencards, &node.card)
+        } else {
+            String::from("(missing)")
+        };
+        let mut line = format!("{}  {}", node.card.name(), title);
+        let count = node.descendants();
+        if count > 0 {
+            line.push_str(&format!("  [{}]", count));
+        }
+        return line;
+    }
+
+    fn print_root(&self, node: &Node) {
+        println!("{}", self.line(node));
+        self.print_children(node, "", 1);
+    }
+
+    fn print_children(&self, node: &Node, prefix: &str, level: usize) {
+        if self.depth.is_some_and(|depth| level > depth) {
+            return;
+        }
+        for (i, child) in node.children.iter().enumerate() {
+            let last = i + 1 == node.children.len();
+            let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+            println!("{}{}{}", prefix, branch, self.line(child));
+            self.print_children(child, &format!("{}{}", prefix, indent), level + 1);
+        }
+    }
+}
+
+pub fn zktree(timeline_file: &Path, args: &varg::Args) {
+    let timeline = model::open_timeline(timeline_file).unwrap();
+    let opencards = cardfolder::CardFolder::from_timeline(&timeline);
+    let arguments = parse_arguments(args, &opencards.config);
+    if let Err(msg) = arguments {
+        eprintln!("{}", msg);
+        return;
+    }
+    let arguments = arguments.unwrap();
+
+    let roots = tree::build(&content::existing_cards(&opencards.folder, &timeline));
+    let printer = Printer { timeline: &timeline, opencards: &opencards, depth: arguments.depth };
+
+    if let Some(root) = arguments.root {
+        match roots.iter().find_map(|node| node.find(&root)) {
+            Some(node) => printer.print_root(node),
+            None => eprintln!("The card {} does not exist", root.name()),
+        }
+    } else {
+        for node in &roots {
+            printer.print_root(node);
+        }
+    }
+}
+